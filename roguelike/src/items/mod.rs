@@ -12,6 +12,7 @@ pub mod view;
 pub const DEFAULT_POTIONS_NUMBER: usize = 10;
 pub const DEFAULT_WEAPON_NUMBER: usize = 0;
 
+#[allow(dead_code)]
 #[derive(Component)]
 pub struct Item {}
 
@@ -176,7 +176,7 @@ mod tests {
         let player_x = 10;
         let player_y = 10;
         let player_pos = Position::new(player_x, player_y);
-        let coward_monster_pos_to_expected_delta = vec![
+        let coward_monster_pos_to_expected_delta = [
             (
                 Position::new(player_x - MONSTER_SEE_DISTANCE, player_y),
                 (0, 0),
@@ -0,0 +1,492 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, IsTerminal},
+    thread::JoinHandle,
+};
+
+use nix::{
+    errno::Errno,
+    sys::{
+        signal::{self, SigHandler, Signal},
+        wait::{waitpid, WaitPidFlag, WaitStatus},
+    },
+    unistd::{self, Pid},
+};
+
+use super::{signals, ExitStatus};
+
+/// Signals that an interactive shell ignores, so that only its foreground job
+/// is stopped with Ctrl-Z or by accessing the terminal from the background.
+const JOB_CONTROL_SIGNALS: [Signal; 3] = [Signal::SIGTSTP, Signal::SIGTTIN, Signal::SIGTTOU];
+
+/// A single member of a job: an external process or a builtin running on a thread.
+pub enum Process {
+    External(Pid),
+    Builtin(JoinHandle<Result<ExitStatus, Box<dyn Error + Send + Sync>>>),
+    /// A command that did not start, e.g. because of a failed redirection.
+    Finished(ExitStatus),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobState {
+    Running,
+    Stopped,
+    Done(ExitStatus),
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobState::Running => write!(f, "Running"),
+            JobState::Stopped => write!(f, "Stopped"),
+            JobState::Done(status) => match (status.signal(), status.code()) {
+                (Some(signal), _) if status.core_dumped() => write!(f, "{signal} (core dumped)"),
+                (Some(signal), _) => write!(f, "{signal}"),
+                (None, Some(0) | None) => write!(f, "Done"),
+                (None, Some(code)) => write!(f, "Exit {code}"),
+            },
+        }
+    }
+}
+
+/// A pipeline or a list started by the shell, tracked until it is done.
+pub struct Job {
+    pub id: usize,
+    /// Process group of the job, set only when job control is enabled.
+    pub pgid: Option<Pid>,
+    pub command: String,
+    pub state: JobState,
+    processes: Vec<(Process, Option<ExitStatus>)>,
+}
+
+impl Job {
+    pub fn new(pgid: Option<Pid>, command: String, processes: Vec<Process>) -> Self {
+        let processes = processes
+            .into_iter()
+            .map(|process| match process {
+                Process::Finished(status) => (process, Some(status)),
+                process => (process, None),
+            })
+            .collect();
+        Self {
+            id: 0,
+            pgid,
+            command,
+            state: JobState::Running,
+            processes,
+        }
+    }
+
+    /// Ids of the external processes of the job.
+    pub fn pids(&self) -> Vec<Pid> {
+        self.processes
+            .iter()
+            .filter_map(|(process, _)| match process {
+                Process::External(pid) => Some(*pid),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Sends a signal to the whole job.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the job has no processes to signal or the signal cannot be delivered.
+    pub fn signal(&self, signal: Signal) -> Result<(), String> {
+        if let Some(pgid) = self.pgid {
+            return signal::killpg(pgid, signal).map_err(|err| err.to_string());
+        }
+        let pids = self.pids();
+        if pids.is_empty() {
+            return Err(String::from("job consists of builtins only"));
+        }
+        for pid in pids {
+            match signal::kill(pid, signal) {
+                Ok(()) | Err(Errno::ESRCH) => {}
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects the statuses of the processes of the job and updates its state.
+    ///
+    /// With `block` it waits until the job is either done or stopped, otherwise
+    /// only the processes that have already changed their state are looked at.
+    pub fn poll(&mut self, block: bool) -> JobState {
+        let mut flags = WaitPidFlag::WUNTRACED;
+        if !block {
+            flags |= WaitPidFlag::WNOHANG | WaitPidFlag::WCONTINUED;
+        }
+
+        for (process, status) in self.processes.iter_mut().filter(|(_, s)| s.is_none()) {
+            match process {
+                Process::External(pid) => match waitpid(*pid, Some(flags)) {
+                    Ok(WaitStatus::Exited(_, code)) => *status = Some(ExitStatus::new(Some(code))),
+                    Ok(WaitStatus::Signaled(_, signal, core_dumped)) => {
+                        // A foreground job killed by an interrupt counts as
+                        // an interrupt of the shell.
+                        if block && signals::is_interrupt(signal) {
+                            signals::interrupt(signal);
+                        }
+                        *status = Some(ExitStatus::signaled(signal, core_dumped));
+                    }
+                    Err(_) => *status = Some(ExitStatus::new(None)),
+                    Ok(WaitStatus::Stopped(..)) => {
+                        self.state = JobState::Stopped;
+                        return self.state;
+                    }
+                    Ok(WaitStatus::Continued(_)) => self.state = JobState::Running,
+                    Ok(_) => {}
+                },
+                Process::Builtin(handle) if block || handle.is_finished() => {
+                    let Process::Builtin(handle) =
+                        std::mem::replace(process, Process::Finished(ExitStatus::new(Some(1))))
+                    else {
+                        unreachable!()
+                    };
+                    let code = match handle.join() {
+                        Ok(Ok(exit_status)) => exit_status,
+                        _ => ExitStatus::new(Some(1)),
+                    };
+                    *status = Some(code);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(Some(last)) = self.processes.last().map(|(_, status)| *status) {
+            if self.processes.iter().all(|(_, status)| status.is_some()) {
+                self.state = JobState::Done(last);
+            }
+        }
+        self.state
+    }
+}
+
+/// Jobs of the shell, addressed by `%n` job specs in builtins.
+#[derive(Default)]
+pub struct JobTable {
+    jobs: Vec<Job>,
+    current: Option<usize>,
+    previous: Option<usize>,
+}
+
+impl JobTable {
+    /// Adds the job to the table making it the current one, and returns its id.
+    pub fn add(&mut self, mut job: Job) -> usize {
+        if job.id == 0 {
+            job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        }
+        let id = job.id;
+        if self.current != Some(id) {
+            self.previous = self.current;
+            self.current = Some(id);
+        }
+        let position = self.jobs.partition_point(|other| other.id < id);
+        self.jobs.insert(position, job);
+        id
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let position = self.jobs.iter().position(|job| job.id == id)?;
+        let job = self.jobs.remove(position);
+        if self.current == Some(id) {
+            self.current = self.previous.take();
+        } else if self.previous == Some(id) {
+            self.previous = None;
+        }
+        if self.previous.is_none() {
+            self.previous = self
+                .jobs
+                .iter()
+                .rev()
+                .map(|job| job.id)
+                .find(|id| Some(*id) != self.current);
+        }
+        Some(job)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    pub fn ids(&self) -> Vec<usize> {
+        self.jobs.iter().map(|job| job.id).collect()
+    }
+
+    /// Finds the id of a job by a job spec: `%n`, `%%`, `%+`, `%-`, `%prefix` or `%?text`.
+    /// A bare number is treated as the id of a process of the job.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for the user if there is no such job.
+    pub fn find(&self, spec: &str) -> Result<usize, String> {
+        let no_such_job = || format!("{spec}: no such job");
+        let Some(spec_body) = spec.strip_prefix('%') else {
+            let pid = spec.parse::<i32>().map_err(|_| no_such_job())?;
+            return self
+                .jobs
+                .iter()
+                .find(|job| job.pids().contains(&Pid::from_raw(pid)))
+                .map(|job| job.id)
+                .ok_or_else(no_such_job);
+        };
+
+        let id = match spec_body {
+            "" | "%" | "+" => self.current,
+            "-" => self.previous,
+            _ => {
+                if let Ok(id) = spec_body.parse::<usize>() {
+                    self.get(id).map(|job| job.id)
+                } else {
+                    let matches: Vec<usize> = match spec_body.strip_prefix('?') {
+                        Some(text) => self
+                            .jobs
+                            .iter()
+                            .filter(|job| job.command.contains(text))
+                            .map(|job| job.id)
+                            .collect(),
+                        None => self
+                            .jobs
+                            .iter()
+                            .filter(|job| job.command.starts_with(spec_body))
+                            .map(|job| job.id)
+                            .collect(),
+                    };
+                    if matches.len() > 1 {
+                        return Err(format!("{spec}: ambiguous job spec"));
+                    }
+                    matches.first().copied()
+                }
+            }
+        };
+        id.ok_or_else(no_such_job)
+    }
+
+    /// Polls all jobs without blocking.
+    pub fn update(&mut self) {
+        for job in &mut self.jobs {
+            job.poll(false);
+        }
+    }
+
+    /// Formats a job the way `jobs` prints it, `long` adds the process ids.
+    pub fn format(&self, job: &Job, long: bool) -> String {
+        let marker = if self.current == Some(job.id) {
+            '+'
+        } else if self.previous == Some(job.id) {
+            '-'
+        } else {
+            ' '
+        };
+        let suffix = if job.state == JobState::Running {
+            " &"
+        } else {
+            ""
+        };
+        let pids = if long {
+            let pids: Vec<String> = job.pids().iter().map(Pid::to_string).collect();
+            format!(" {}", pids.join(" "))
+        } else {
+            String::new()
+        };
+        format!(
+            "[{}]{}{}  {:<24}{}{}",
+            job.id,
+            marker,
+            pids,
+            job.state.to_string(),
+            job.command,
+            suffix
+        )
+    }
+
+    /// Removes the jobs that are done except the current one, which `wait $!`
+    /// may still ask for, without reporting them.
+    pub fn forget_finished(&mut self) {
+        self.update();
+        let finished: Vec<usize> = self
+            .jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Done(_)) && self.current != Some(job.id))
+            .map(|job| job.id)
+            .collect();
+        for id in finished {
+            self.remove(id);
+        }
+    }
+
+    /// Removes the jobs that are done and returns their descriptions.
+    pub fn take_finished(&mut self) -> Vec<String> {
+        self.update();
+        let finished: Vec<usize> = self
+            .jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Done(_)))
+            .map(|job| job.id)
+            .collect();
+        finished
+            .into_iter()
+            .map(|id| {
+                let line = self.format(self.get(id).unwrap(), false);
+                self.remove(id);
+                line
+            })
+            .collect()
+    }
+}
+
+/// The controlling terminal of an interactive shell.
+#[derive(Debug, Clone, Copy)]
+pub struct Terminal {
+    shell_pgid: Pid,
+}
+
+impl Terminal {
+    /// Takes control over the terminal connected to stdin, if there is one.
+    ///
+    /// The shell waits until it is in the foreground, moves into its own
+    /// process group and ignores the job control signals.
+    pub fn acquire() -> Option<Self> {
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            return None;
+        }
+        loop {
+            let pgrp = unistd::getpgrp();
+            match unistd::tcgetpgrp(&stdin) {
+                Ok(foreground) if foreground == pgrp => break,
+                Ok(_) => {
+                    let _ = signal::killpg(pgrp, Signal::SIGTTIN);
+                }
+                Err(_) => return None,
+            }
+        }
+        for signal in JOB_CONTROL_SIGNALS {
+            // SAFETY: ignoring a signal does not run any code in a handler.
+            unsafe {
+                let _ = signal::signal(signal, SigHandler::SigIgn);
+            }
+        }
+        let shell_pgid = unistd::getpid();
+        if unistd::setpgid(shell_pgid, shell_pgid).is_err() {
+            // Session leaders cannot change their group and already own it.
+            return Some(Self {
+                shell_pgid: unistd::getpgrp(),
+            });
+        }
+        unistd::tcsetpgrp(&stdin, shell_pgid).ok()?;
+        Some(Self { shell_pgid })
+    }
+
+    /// Makes the process group the foreground one.
+    pub fn give(&self, pgid: Pid) {
+        let _ = unistd::tcsetpgrp(io::stdin(), pgid);
+    }
+
+    /// Takes the terminal back to the shell.
+    pub fn reclaim(&self) {
+        let _ = unistd::tcsetpgrp(io::stdin(), self.shell_pgid);
+    }
+
+    /// Restores the default handling of the job control signals.
+    ///
+    /// Ignored signals are inherited through `exec`, so this must run in a child
+    /// process before it executes a command.
+    pub fn reset_signals() -> io::Result<()> {
+        for signal in JOB_CONTROL_SIGNALS {
+            // SAFETY: sigaction is async-signal-safe, no handler code is installed.
+            unsafe { signal::signal(signal, SigHandler::SigDfl) }?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn builtin_job(command: &str, code: i32) -> Job {
+        let handle = thread::spawn(move || Ok(ExitStatus::new(Some(code))));
+        Job::new(None, String::from(command), vec![Process::Builtin(handle)])
+    }
+
+    #[test]
+    fn test_find_job_specs() {
+        let mut table = JobTable::default();
+        let first = table.add(builtin_job("sleep 10", 0));
+        let second = table.add(builtin_job("make all", 0));
+        let third = table.add(builtin_job("make test", 0));
+
+        assert_eq!(table.find("%1"), Ok(first));
+        assert_eq!(table.find("%%"), Ok(third));
+        assert_eq!(table.find("%+"), Ok(third));
+        assert_eq!(table.find("%-"), Ok(second));
+        assert_eq!(table.find("%sl"), Ok(first));
+        assert_eq!(table.find("%?test"), Ok(third));
+        assert!(table.find("%make").is_err());
+        assert!(table.find("%4").is_err());
+
+        table.remove(third);
+        assert_eq!(table.find("%%"), Ok(second));
+        assert_eq!(table.find("%-"), Ok(first));
+        assert_eq!(table.add(builtin_job("true", 0)), 3);
+    }
+
+    #[test]
+    fn test_forget_finished_keeps_current_job() {
+        let mut table = JobTable::default();
+        let first = table.add(builtin_job("true", 0));
+        let second = table.add(builtin_job("false", 1));
+        thread::sleep(std::time::Duration::from_millis(50));
+
+        table.forget_finished();
+        assert_eq!(table.ids(), vec![second]);
+        assert!(table.get(first).is_none());
+        assert_eq!(
+            table.get(second).map(|job| job.state),
+            Some(JobState::Done(ExitStatus::new(Some(1))))
+        );
+    }
+
+    #[test]
+    fn test_take_finished_reports_status() {
+        let mut table = JobTable::default();
+        let id = table.add(builtin_job("false", 1));
+        table.get_mut(id).unwrap().poll(true);
+
+        assert_eq!(
+            table.take_finished(),
+            vec![String::from("[1]+  Exit 1                  false")]
+        );
+        assert!(table.ids().is_empty());
+    }
+
+    #[test]
+    fn test_done_state_shows_signal() {
+        let done = |status| JobState::Done(status).to_string();
+        assert_eq!(done(ExitStatus::new(Some(0))), "Done");
+        assert_eq!(done(ExitStatus::new(Some(130))), "Exit 130");
+        assert_eq!(
+            done(ExitStatus::signaled(Signal::SIGTERM, false)),
+            "SIGTERM"
+        );
+        assert_eq!(
+            done(ExitStatus::signaled(Signal::SIGSEGV, true)),
+            "SIGSEGV (core dumped)"
+        );
+        assert_eq!(
+            ExitStatus::signaled(Signal::SIGKILL, false).code(),
+            Some(137)
+        );
+    }
+}
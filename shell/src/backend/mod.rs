@@ -1,171 +1,968 @@
 use std::{
-    collections::VecDeque,
+    collections::HashMap,
     error::Error,
+    fmt,
+    fs::File,
     io::{self, Read, Write},
-    process::{Command as ProcessCommand, Stdio},
-    thread::{self, JoinHandle},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::Command as ProcessCommand,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread,
 };
 
-use crate::ir::CallCommand;
-use crate::ir::PipeCommand;
+use nix::{sys::signal::Signal, unistd::Pid};
 
-pub struct Backend;
+use crate::frontend::{Control, Frontend, ParseError, ShellCommandInterm};
+use crate::ir::{AndOrList, CommandList, CompoundCommand, ListOperator, Pipeline};
+use crate::ir::{BuiltinCommand, CallCommand, Command, PipeCommand};
+use jobs::{Job, Process, Terminal};
+pub use jobs::{JobState, JobTable};
+use stream::StdStreams;
+pub use stream::Stream;
+
+mod jobs;
+pub mod signals;
+mod stream;
+
+#[derive(Clone)]
+pub struct Backend {
+    jobs: Arc<Mutex<JobTable>>,
+    /// Set when job control is enabled.
+    terminal: Option<Terminal>,
+}
 
 /// Represents the exit status of a command execution.
-#[derive(Debug, Default)]
+///
+/// A command terminated by a signal has the code `128+N`, like in POSIX
+/// shells. The code is unknown if the status of a process could not be
+/// collected.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct ExitStatus {
     code: Option<i32>,
+    signal: Option<Signal>,
+    core_dumped: bool,
 }
 
 impl ExitStatus {
+    /// The code of a command that was found but cannot be executed.
+    pub const NOT_EXECUTABLE: i32 = 126;
+    /// The code of a command that was not found.
+    pub const NOT_FOUND: i32 = 127;
+
     pub fn new(code: Option<i32>) -> Self {
-        Self { code }
+        Self {
+            code,
+            signal: None,
+            core_dumped: false,
+        }
+    }
+
+    /// The status of a command terminated by `signal`.
+    pub fn signaled(signal: Signal, core_dumped: bool) -> Self {
+        Self {
+            code: Some(128 + signal as i32),
+            signal: Some(signal),
+            core_dumped,
+        }
     }
 
     pub fn code(&self) -> Option<i32> {
         self.code
     }
+
+    /// The signal that terminated the command, if any.
+    pub fn signal(&self) -> Option<Signal> {
+        self.signal
+    }
+
+    pub fn core_dumped(&self) -> bool {
+        self.core_dumped
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.signal, self.code) {
+            (Some(signal), _) => {
+                write!(f, "killed by {signal}")?;
+                if self.core_dumped {
+                    write!(f, " (core dumped)")?;
+                }
+                Ok(())
+            }
+            (None, Some(code)) => write!(f, "exited with code {code}"),
+            (None, None) => write!(f, "exited with an unknown status"),
+        }
+    }
+}
+
+/// The shell as seen by builtins, which run inside the shell process.
+///
+/// Builtins of a pipeline or of a background job get a copy of the frontend,
+/// so their changes are not visible to the shell, just like in a subshell.
+pub struct Shell<'a> {
+    pub frontend: &'a mut Frontend,
+    pub backend: &'a Backend,
+}
+
+impl Shell<'_> {
+    /// The working directory of the shell, which `cd` changes.
+    pub fn current_dir(&self) -> PathBuf {
+        self.frontend.current_dir()
+    }
+
+    /// Resolves a path given to a builtin against the working directory of the shell.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.current_dir().join(path)
+    }
 }
 
 /// Represents the backend that handles the execution of shell commands.
 impl Backend {
     pub fn new() -> Self {
-        Self
+        Self {
+            jobs: Arc::default(),
+            terminal: None,
+        }
+    }
+
+    /// Creates a backend with job control if the shell runs on a terminal.
+    ///
+    /// Pipelines are then started in their own process groups, and the terminal
+    /// is handed over to the foreground one, so that Ctrl-C and Ctrl-Z reach the
+    /// foreground job instead of the shell.
+    pub fn with_job_control() -> Self {
+        Self {
+            terminal: Terminal::acquire(),
+            ..Self::new()
+        }
+    }
+
+    pub fn jobs(&self) -> MutexGuard<'_, JobTable> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Reaps the jobs that finished since the last call. With job control they are
+    /// printed, otherwise they are dropped silently.
+    pub fn report_finished_jobs(&self) {
+        if self.terminal.is_none() {
+            self.jobs().forget_finished();
+            return;
+        }
+        for line in self.jobs().take_finished() {
+            eprintln!("{line}");
+        }
+    }
+
+    /// Executes all statements of a command list one after another.
+    ///
+    /// Each pipeline is compiled by `frontend` right before it runs, and pipelines
+    /// joined with `&&` or `||` are skipped according to the exit status of the
+    /// previous one. Lists terminated with `&` are started as background jobs.
+    /// Errors of a single pipeline are reported on stderr and do not stop the rest
+    /// of the list, a pending `break` or `continue` does.
+    ///
+    /// # Returns
+    ///
+    /// The exit status of the last pipeline that ran, or success for an empty list.
+    pub fn exec_list<Stdin, Stdout, Stderr>(
+        &self,
+        list: CommandList,
+        frontend: &mut Frontend,
+        stdin: Stdin,
+        stdout: Stdout,
+        stderr: Stderr,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>>
+    where
+        Stdin: Into<Stream>,
+        Stdout: Into<Stream>,
+        Stderr: Into<Stream>,
+    {
+        let (stdin, stdout, stderr) = (stdin.into(), stdout.into(), stderr.into());
+        let mut status = ExitStatus::new(Some(0));
+        for and_or in list.items {
+            status = if and_or.background {
+                self.exec_background(and_or, frontend, &stdin, &stdout, &stderr)?
+            } else {
+                self.exec_and_or(and_or, frontend, &stdin, &stdout, &stderr)?
+            };
+            if frontend.env().control().is_some() || signals::interrupted().is_some() {
+                break;
+            }
+        }
+        Ok(status)
+    }
+
+    fn exec_and_or(
+        &self,
+        and_or: AndOrList,
+        frontend: &mut Frontend,
+        stdin: &Stream,
+        stdout: &Stream,
+        stderr: &Stream,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let mut status = self.exec_pipeline(and_or.first, frontend, stdin, stdout, stderr)?;
+        for (operator, pipeline) in and_or.rest {
+            if frontend.env().control().is_some() || signals::interrupted().is_some() {
+                break;
+            }
+            let should_run = match operator {
+                ListOperator::And => status.success(),
+                ListOperator::Or => !status.success(),
+            };
+            if should_run {
+                status = self.exec_pipeline(pipeline, frontend, stdin, stdout, stderr)?;
+            }
+        }
+        Ok(status)
+    }
+
+    fn exec_pipeline(
+        &self,
+        pipeline: Pipeline,
+        frontend: &mut Frontend,
+        stdin: &Stream,
+        stdout: &Stream,
+        stderr: &Stream,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let negated = pipeline.negated;
+        let mut substitute =
+            |list, subshell: &mut Frontend| self.substitute(list, subshell, stdin, stderr);
+        let status = match frontend.compile(pipeline, &mut substitute) {
+            Ok(pipe) => {
+                let (input, output, error) =
+                    (stdin.try_clone()?, stdout.try_clone()?, stderr.try_clone()?);
+                self.exec(pipe, frontend, input, output, error)
+                    .unwrap_or_else(|err| {
+                        report(stderr, format_args!("shell error: {err}"));
+                        ExitStatus::new(Some(1))
+                    })
+            }
+            Err(err) => {
+                report(stderr, err);
+                ExitStatus::new(Some(1))
+            }
+        };
+        let status = match negated {
+            true => ExitStatus::new(Some(if status.success() { 1 } else { 0 })),
+            false => status,
+        };
+        frontend.env_mut().set_status(status.code().unwrap_or(1));
+        Ok(status)
+    }
+
+    /// Runs the commands of a command substitution without job control and
+    /// returns their standard output and exit status. An `exit` ends only the
+    /// substitution.
+    fn substitute(
+        &self,
+        list: CommandList,
+        frontend: &mut Frontend,
+        stdin: &Stream,
+        stderr: &Stream,
+    ) -> Result<(String, i32), ParseError> {
+        let error = |err: Box<dyn Error + Send + Sync>| ParseError::from(err.to_string());
+        let stdin = stdin.try_clone().map_err(|err| error(err.into()))?;
+        let stderr = stderr.try_clone().map_err(|err| error(err.into()))?;
+        let (mut reader, writer) = os_pipe::pipe().map_err(|err| error(err.into()))?;
+        let backend = Backend {
+            jobs: self.jobs.clone(),
+            terminal: None,
+        };
+
+        let mut output = Vec::new();
+        let status = thread::scope(|scope| {
+            let handle = scope.spawn(move || {
+                let status = backend.exec_list(list, frontend, stdin, writer, stderr)?;
+                Ok(subshell_status(frontend, status))
+            });
+            let read = reader.read_to_end(&mut output);
+            let status = handle
+                .join()
+                .map_err(|_| "command substitution panicked")?
+                .map_err(error)?;
+            read.map_err(|err| error(err.into()))?;
+            Ok::<_, ParseError>(status)
+        })?;
+        let output = String::from_utf8_lossy(&output).into_owned();
+        Ok((output, status.code().unwrap_or(1)))
+    }
+
+    /// Starts an and-or list as a background job.
+    ///
+    /// A single pipeline becomes a job of its processes. Longer lists run on a
+    /// separate thread with a copy of the frontend, like a subshell.
+    fn exec_background(
+        &self,
+        mut and_or: AndOrList,
+        frontend: &mut Frontend,
+        stdin: &Stream,
+        stdout: &Stream,
+        stderr: &Stream,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let command = and_or.to_string();
+        // Without job control background jobs must not compete for the input of the shell.
+        let stdin = match self.terminal {
+            Some(_) => stdin.try_clone()?,
+            None => File::open("/dev/null")?.into(),
+        };
+        let stdout = stdout.try_clone()?;
+        let stderr = stderr.try_clone()?;
+
+        let job = if and_or.rest.is_empty() {
+            let mut substitute =
+                |list, subshell: &mut Frontend| self.substitute(list, subshell, &stdin, &stderr);
+            let pipe = match frontend.compile(and_or.first, &mut substitute) {
+                Ok(pipe) => pipe,
+                Err(err) => {
+                    report(&stderr, err);
+                    frontend.env_mut().set_status(1);
+                    return Ok(ExitStatus::new(Some(1)));
+                }
+            };
+            let (pgid, processes) = self.start(pipe, frontend, stdin, stdout, &stderr)?;
+            Job::new(pgid, command, processes)
+        } else {
+            and_or.background = false;
+            let mut frontend = frontend.clone();
+            let backend = Backend {
+                jobs: self.jobs.clone(),
+                terminal: None,
+            };
+            let handle = thread::spawn(move || {
+                let status =
+                    backend.exec_and_or(and_or, &mut frontend, &stdin, &stdout, &stderr)?;
+                Ok(subshell_status(&frontend, status))
+            });
+            Job::new(None, command, vec![Process::Builtin(handle)])
+        };
+
+        let pid = job.pids().last().copied();
+        let id = self.jobs().add(job);
+        if let Some(pid) = pid {
+            frontend.env_mut().set_last_background(pid.as_raw() as u32);
+        }
+        frontend.env_mut().set_status(0);
+        if self.terminal.is_some() {
+            match pid {
+                Some(pid) => eprintln!("[{id}] {pid}"),
+                None => eprintln!("[{id}]"),
+            }
+        }
+        Ok(ExitStatus::new(Some(0)))
     }
 
     /// Executes a sequence of shell commands connected by pipes with specified input/output streams.
     ///
     /// This method sets up and runs a sequence of commands (provided as `PipeCommand`) that form
     /// a pipeline where the output of each command is connected to the input of the next.
+    /// A single builtin runs directly in the shell with access to `frontend`, builtins
+    /// of longer pipelines run on threads with copies of it. External commands start
+    /// in the working directory of the shell.
     ///
     /// # Returns
     ///
-    /// Returns `Ok(Some(Output))` containing the output of the last command, if the execution is successful.
-    /// Returns `Err` if an error occurs during the setup or execution of the pipeline, such as if
-    /// the command fails to start.
-    ///
-    /// The function will also return an error if the `PipeCommand` contains fewer than two commands
-    /// since a meaningful pipeline requires at least two commands for data flow.
+    /// Returns the exit status of the last command, or `$?` for a pipeline without
+    /// commands, which the compiler sets. If the pipeline is stopped with
+    /// Ctrl-Z, it is moved to the job table and `128 + SIGTSTP` is returned.
     ///
     /// # Error Handling
     ///
-    /// This function captures standard error of the processes by inheriting stderr from the parent.
-    /// Errors such as failure in spawning a command or incorrect setup will return a boxed
-    /// error encompassing the issue.
-    ///
-    /// # Panics
-    ///
-    /// This function may panic if called with invalid `Stdio` objects (e.g., if trying to use the same
-    /// `Stdio` handle multiple times or after it has been transformed into a file descriptor).
-    pub fn exec<Stdin, Stdout>(
+    /// Standard error of the commands is `stderr` unless it is redirected.
+    /// Commands that fail to start report the error on their stderr and exit with code 127
+    /// if they were not found, or 126 if they cannot be executed.
+    /// Errors in the setup of the pipeline itself are returned as a boxed error.
+    pub fn exec<Stdin, Stdout, Stderr>(
         &self,
         mut pipe: PipeCommand,
+        frontend: &mut Frontend,
         stdin: Stdin,
         stdout: Stdout,
+        stderr: Stderr,
     ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>>
     where
-        Stdin: Into<Stdio> + Read + Send + 'static,
-        Stdout: Into<Stdio> + Write + Send + 'static,
+        Stdin: Into<Stream>,
+        Stdout: Into<Stream>,
+        Stderr: Into<Stream>,
     {
+        let (stdin, stdout, stderr) = (stdin.into(), stdout.into(), stderr.into());
         if pipe.commands.is_empty() {
-            return Ok(ExitStatus::new(Some(0)));
-        } else if pipe.commands.len() == 1 {
-            let command = pipe.commands.pop().unwrap();
-            let join_res = self.spawn_command(command, stdin, stdout).join();
-            return match join_res {
-                Ok(res) => res,
-                Err(err) => Err(format!("{:?}", err).into()),
+            // The compiler left the status of the last command substitution.
+            return Ok(ExitStatus::new(Some(frontend.env().status())));
+        }
+        if pipe.commands.len() == 1 && !matches!(pipe.commands[0].command, Command::Call) {
+            let call_command = pipe.commands.pop().unwrap();
+            let mut streams = StdStreams::new(stdin, stdout, stderr);
+            if let Err(err) = streams.apply(&call_command.redirects, &frontend.current_dir()) {
+                let [_, _, mut stderr] = streams.into_inner();
+                writeln!(stderr, "shell: {err}")?;
+                return Ok(ExitStatus::new(Some(1)));
+            }
+            let builtin = match call_command.command {
+                Command::Builtin(builtin) => builtin,
+                Command::Compound(compound) => {
+                    return self.exec_compound(*compound, frontend, streams)
+                }
+                Command::Function(body) => {
+                    return self.exec_function(
+                        *body,
+                        call_command.argv,
+                        &call_command.envs,
+                        frontend,
+                        streams,
+                    )
+                }
+                Command::Call => unreachable!(),
             };
+            let mut shell = Shell {
+                frontend,
+                backend: self,
+            };
+            return run_builtin(
+                builtin,
+                call_command.argv,
+                &call_command.envs,
+                &mut shell,
+                streams,
+            );
         }
 
-        let mut commands = VecDeque::new();
-        let mut pipe_commands = pipe.commands.drain(..).collect::<VecDeque<_>>();
+        let command = describe(&pipe);
+        let (pgid, processes) = self.start(pipe, frontend, stdin, stdout, &stderr)?;
+        Ok(self.wait_foreground(Job::new(pgid, command, processes)))
+    }
+
+    /// Evaluates a compound command in the shell, with the standard streams
+    /// its redirections resulted in.
+    ///
+    /// The exit status is the one of the last list that ran in a branch or in
+    /// the body of a loop, or success if none did. Words that fail to expand are
+    /// reported on stderr and result in exit code 1.
+    fn exec_compound(
+        &self,
+        compound: CompoundCommand,
+        frontend: &mut Frontend,
+        streams: StdStreams,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let [stdin, stdout, stderr] = streams.into_inner();
+        let run = |list: CommandList,
+                   frontend: &mut Frontend|
+         -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+            let (input, output, error) =
+                (stdin.try_clone()?, stdout.try_clone()?, stderr.try_clone()?);
+            self.exec_list(list, frontend, input, output, error)
+        };
+        let mut substitute =
+            |list, subshell: &mut Frontend| self.substitute(list, subshell, &stdin, &stderr);
+        let success = ExitStatus::new(Some(0));
 
-        let (mut reader, writer) = os_pipe::pipe()?;
-        commands.push_back(self.spawn_command(pipe_commands.pop_front().unwrap(), stdin, writer));
+        let status = match compound {
+            CompoundCommand::Brace(list) => run(list, frontend)?,
+            CompoundCommand::Subshell(list) => {
+                let mut subshell = frontend.clone();
+                let status = run(list, &mut subshell)?;
+                subshell_status(&subshell, status)
+            }
+            CompoundCommand::If {
+                conditionals,
+                else_branch,
+            } => {
+                let mut branch = else_branch;
+                for (condition, body) in conditionals {
+                    let status = run(condition, frontend)?;
+                    if frontend.env().control().is_some() {
+                        return Ok(status);
+                    }
+                    if status.success() {
+                        branch = Some(body);
+                        break;
+                    }
+                }
+                match branch {
+                    Some(body) => run(body, frontend)?,
+                    None => success,
+                }
+            }
+            CompoundCommand::Loop {
+                until,
+                condition,
+                body,
+            } => {
+                frontend.env_mut().enter_loop();
+                let mut run_loop = || {
+                    let mut status = success;
+                    loop {
+                        let guard = run(condition.clone(), frontend)?;
+                        match take_control(frontend) {
+                            Flow::Break => break,
+                            Flow::Continue => continue,
+                            Flow::Proceed if guard.success() == until => break,
+                            Flow::Proceed => {}
+                        }
+                        status = run(body.clone(), frontend)?;
+                        if take_control(frontend) == Flow::Break {
+                            break;
+                        }
+                    }
+                    Ok(status)
+                };
+                let result: Result<_, Box<dyn Error + Sync + Send>> = run_loop();
+                frontend.env_mut().leave_loop();
+                result?
+            }
+            CompoundCommand::For { name, words, body } => {
+                let values = match words {
+                    Some(words) => match frontend.expand_arguments(words, &mut substitute) {
+                        Ok(values) => values,
+                        Err(err) => {
+                            report(&stderr, err);
+                            return Ok(ExitStatus::new(Some(1)));
+                        }
+                    },
+                    None => frontend.env().positional().to_vec(),
+                };
+                frontend.env_mut().enter_loop();
+                let run_loop = || {
+                    let mut status = success;
+                    for value in values {
+                        frontend.env_mut().set(&name, value);
+                        status = run(body.clone(), frontend)?;
+                        if take_control(frontend) == Flow::Break {
+                            break;
+                        }
+                    }
+                    Ok(status)
+                };
+                let result: Result<_, Box<dyn Error + Sync + Send>> = run_loop();
+                frontend.env_mut().leave_loop();
+                result?
+            }
+            CompoundCommand::Case { word, arms } => {
+                let word = match frontend.expand_string(word, &mut substitute) {
+                    Ok(word) => word,
+                    Err(err) => {
+                        report(&stderr, err);
+                        return Ok(ExitStatus::new(Some(1)));
+                    }
+                };
+                let mut status = success;
+                'arms: for (patterns, body) in arms {
+                    for pattern in patterns {
+                        match frontend.expand_glob(pattern, &mut substitute) {
+                            Ok(pattern) if pattern.matches(&word) => {
+                                status = run(body, frontend)?;
+                                break 'arms;
+                            }
+                            Ok(_) => {}
+                            Err(err) => {
+                                report(&stderr, err);
+                                return Ok(ExitStatus::new(Some(1)));
+                            }
+                        }
+                    }
+                }
+                status
+            }
+        };
+        Ok(status)
+    }
 
-        while pipe_commands.len() != 1 {
-            let next_cmd = pipe_commands.pop_front().unwrap();
-            let (next_reader, next_writer) = os_pipe::pipe()?;
-            commands.push_back(self.spawn_command(next_cmd, reader, next_writer));
-            reader = next_reader;
+    /// Calls a shell function with the arguments of `argv`.
+    ///
+    /// The function gets its own positional parameters and `local` variables
+    /// until it returns. Variables of `envs` that differ from the shell
+    /// environment are exported while it runs, like for builtins.
+    fn exec_function(
+        &self,
+        body: ShellCommandInterm,
+        argv: Vec<String>,
+        envs: &HashMap<String, String>,
+        frontend: &mut Frontend,
+        streams: StdStreams,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let [stdin, stdout, stderr] = streams.into_inner();
+        let saved = frontend.env_mut().export_temporarily(envs);
+        frontend
+            .env_mut()
+            .enter_function(argv.into_iter().skip(1).collect());
+        let body = Pipeline {
+            negated: false,
+            commands: vec![body],
+        };
+        let result = self.exec_pipeline(body, frontend, &stdin, &stdout, &stderr);
+        let env = frontend.env_mut();
+        if env.control() == Some(Control::Return) {
+            env.set_control(None);
         }
+        env.leave_function();
+        env.restore(saved);
+        result
+    }
 
-        commands.push_back(self.spawn_command(pipe_commands.pop_front().unwrap(), reader, stdout));
+    /// Spawns all commands of the pipeline and returns its process group with the processes.
+    fn start(
+        &self,
+        pipe: PipeCommand,
+        frontend: &Frontend,
+        stdin: Stream,
+        stdout: Stream,
+        stderr: &Stream,
+    ) -> Result<(Option<Pid>, Vec<Process>), Box<dyn Error + Sync + Send>> {
+        let mut pgid = None;
+        let mut processes = Vec::new();
+        let mut input = stdin;
+        let mut stdout = Some(stdout);
+        let count = pipe.commands.len();
+        for (index, call_command) in pipe.commands.into_iter().enumerate() {
+            let (output, next_input) = if index + 1 == count {
+                (stdout.take().unwrap(), Stream::Closed)
+            } else {
+                let (reader, writer) = os_pipe::pipe()?;
+                (writer.into(), reader.into())
+            };
+            let streams = StdStreams::new(input, output, stderr.try_clone()?);
+            processes.push(self.spawn_command(call_command, frontend, streams, &mut pgid));
+            input = next_input;
+        }
+        Ok((pgid, processes))
+    }
+
+    /// Waits for a job in the foreground, handing the terminal over to it.
+    fn wait_foreground(&self, mut job: Job) -> ExitStatus {
+        if let (Some(terminal), Some(pgid)) = (self.terminal, job.pgid) {
+            terminal.give(pgid);
+        }
+        signals::set_foreground(job.pgid);
+        let state = job.poll(true);
+        signals::set_foreground(None);
+        if let Some(terminal) = self.terminal {
+            terminal.reclaim();
+        }
 
-        while commands.len() != 1 {
-            let command = commands.pop_front().unwrap();
-            let _ = command.join().map_err(|err| format!("{:?}", err))?;
+        match state {
+            JobState::Done(status) => status,
+            JobState::Stopped | JobState::Running => {
+                let mut jobs = self.jobs();
+                let id = jobs.add(job);
+                eprintln!("\n{}", jobs.format(jobs.get(id).unwrap(), false));
+                ExitStatus::new(Some(128 + Signal::SIGTSTP as i32))
+            }
         }
-        commands
-            .pop_front()
-            .unwrap()
-            .join()
-            .map_err(|err| format!("{:?}", err))?
     }
 
-    /// Executes given ir::Command
+    /// Continues a job in the foreground and waits for it, as `fg` does.
     ///
     /// # Errors
     ///
-    /// This function will return an UnimplementedError for two or more commands in
-    /// PipeCommand. Moreover, it will return any OS errors encountered during spawn
-    /// of subprocess
-    pub fn spawn_command<Stdin, Stdout>(
+    /// Returns a message for the user if there is no such job or it cannot be continued.
+    pub fn foreground(&self, id: usize) -> Result<ExitStatus, String> {
+        let mut job = self
+            .jobs()
+            .remove(id)
+            .ok_or_else(|| format!("%{id}: no such job"))?;
+        eprintln!("{}", job.command);
+        if job.state == JobState::Stopped {
+            if let (Some(terminal), Some(pgid)) = (self.terminal, job.pgid) {
+                terminal.give(pgid);
+            }
+            job.signal(Signal::SIGCONT)?;
+            job.state = JobState::Running;
+        }
+        Ok(self.wait_foreground(job))
+    }
+
+    /// Continues a stopped job in the background, as `bg` does, and returns its command.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for the user if there is no such job or it cannot be continued.
+    pub fn background(&self, id: usize) -> Result<String, String> {
+        let mut jobs = self.jobs();
+        let job = jobs
+            .get_mut(id)
+            .ok_or_else(|| format!("%{id}: no such job"))?;
+        if job.state == JobState::Stopped {
+            job.signal(Signal::SIGCONT)?;
+            job.state = JobState::Running;
+        }
+        Ok(job.command.clone())
+    }
+
+    /// Waits until a job is done and removes it from the job table, as `wait` does.
+    pub fn wait_job(&self, id: usize) -> Option<ExitStatus> {
+        let mut job = self.jobs().remove(id)?;
+        match job.poll(true) {
+            JobState::Done(status) => Some(status),
+            JobState::Stopped | JobState::Running => {
+                self.jobs().add(job);
+                Some(ExitStatus::new(Some(128 + Signal::SIGTSTP as i32)))
+            }
+        }
+    }
+
+    /// Starts given ir::Command
+    ///
+    /// Redirections of the command are applied on top of the given `streams`. A
+    /// redirection that fails is reported on the command's stderr and results in
+    /// exit code 1, the command is not started then.
+    ///
+    /// With job control external commands join the process group `pgid`, the first
+    /// one creates it.
+    fn spawn_command(
         &self,
         call_command: CallCommand,
-        stdin: Stdin,
-        stdout: Stdout,
-    ) -> JoinHandle<Result<ExitStatus, Box<dyn Error + Send + Sync>>>
-    where
-        Stdin: Into<Stdio> + Read + Send + 'static,
-        Stdout: Into<Stdio> + Write + Send + 'static,
-    {
+        frontend: &Frontend,
+        mut streams: StdStreams,
+        pgid: &mut Option<Pid>,
+    ) -> Process {
+        if let Err(err) = streams.apply(&call_command.redirects, &frontend.current_dir()) {
+            let [_, _, mut stderr] = streams.into_inner();
+            let _ = writeln!(stderr, "shell: {err}");
+            return Process::Finished(ExitStatus::new(Some(1)));
+        }
+
         match call_command.command {
-            crate::ir::Command::Call => {
+            Command::Call => {
+                let closed = streams.closed();
+                let mut error_stream = streams.stderr().try_clone();
+                let [stdin, stdout, stderr] = match streams.into_stdio() {
+                    Ok(stdio) => stdio,
+                    Err(err) => {
+                        if let Ok(stderr) = &mut error_stream {
+                            let _ = writeln!(stderr, "shell: {err}");
+                        }
+                        return Process::Finished(ExitStatus::new(Some(1)));
+                    }
+                };
                 let mut command = ProcessCommand::new(&call_command.argv[0]);
 
                 command
                     .args(&call_command.argv[1..])
                     .stdin(stdin)
                     .stdout(stdout)
-                    .stderr(Stdio::inherit())
+                    .stderr(stderr)
+                    .current_dir(frontend.current_dir())
+                    .env_clear()
                     .envs(call_command.envs);
+                if self.terminal.is_some() {
+                    command.process_group(pgid.map_or(0, Pid::as_raw));
+                    // SAFETY: the closure only restores signal dispositions with sigaction.
+                    unsafe {
+                        command.pre_exec(Terminal::reset_signals);
+                    }
+                }
+                if !closed.is_empty() {
+                    // SAFETY: the closure only calls close, it does not allocate.
+                    unsafe {
+                        command.pre_exec(move || {
+                            for &fd in &closed {
+                                nix::unistd::close(fd)?;
+                            }
+                            Ok(())
+                        });
+                    }
+                }
 
-                thread::spawn(move || {
-                    command
-                        .spawn()?
-                        .wait()
-                        .map(|status| ExitStatus::new(status.code()))
-                        .map_err(|err| format!("{}", err).into())
-                })
-            }
-            crate::ir::Command::Builtin(builtin_command) => thread::spawn(move || {
-                let mut stdin = stdin;
-                let mut stderr = io::stderr();
-                let mut stdout = stdout;
-                match builtin_command.exec(call_command.argv, &mut stdin, &mut stderr, &mut stdout)
-                {
-                    Ok(_) => Ok(ExitStatus::new(Some(0))),
+                match command.spawn() {
+                    Ok(child) => {
+                        let pid = Pid::from_raw(child.id() as i32);
+                        if self.terminal.is_some() && pgid.is_none() {
+                            *pgid = Some(pid);
+                        }
+                        Process::External(pid)
+                    }
                     Err(err) => {
-                        writeln!(stderr, "{}", err)
-                            .map_err(|_| "failed to write error to stderr")?;
-                        Ok(ExitStatus::new(Some(1)))
+                        let (status, message) = spawn_failure(&call_command.argv[0], &err);
+                        if let Ok(stderr) = &mut error_stream {
+                            let _ = writeln!(stderr, "shell: {}: {message}", call_command.argv[0]);
+                        }
+                        Process::Finished(status)
                     }
                 }
-            }),
+            }
+            Command::Builtin(builtin_command) => {
+                let mut frontend = frontend.clone();
+                let backend = self.clone();
+                Process::Builtin(thread::spawn(move || {
+                    let mut shell = Shell {
+                        frontend: &mut frontend,
+                        backend: &backend,
+                    };
+                    let status = run_builtin(
+                        builtin_command,
+                        call_command.argv,
+                        &call_command.envs,
+                        &mut shell,
+                        streams,
+                    )?;
+                    Ok(subshell_status(&frontend, status))
+                }))
+            }
+            Command::Compound(compound) => {
+                let mut frontend = frontend.clone();
+                let backend = Backend {
+                    jobs: self.jobs.clone(),
+                    terminal: None,
+                };
+                Process::Builtin(thread::spawn(move || {
+                    let status = backend.exec_compound(*compound, &mut frontend, streams)?;
+                    Ok(subshell_status(&frontend, status))
+                }))
+            }
+            Command::Function(body) => {
+                let mut frontend = frontend.clone();
+                let backend = Backend {
+                    jobs: self.jobs.clone(),
+                    terminal: None,
+                };
+                Process::Builtin(thread::spawn(move || {
+                    let status = backend.exec_function(
+                        *body,
+                        call_command.argv,
+                        &call_command.envs,
+                        &mut frontend,
+                        streams,
+                    )?;
+                    Ok(subshell_status(&frontend, status))
+                }))
+            }
+        }
+    }
+}
+
+/// Runs a builtin, reporting its error on its stderr.
+///
+/// Variables of `envs` that differ from the shell environment, i.e. prefix
+/// assignments, are exported only while the builtin runs.
+fn run_builtin(
+    builtin_command: Box<dyn BuiltinCommand + Send>,
+    argv: Vec<String>,
+    envs: &HashMap<String, String>,
+    shell: &mut Shell,
+    streams: StdStreams,
+) -> Result<ExitStatus, Box<dyn Error + Send + Sync>> {
+    let [mut stdin, mut stdout, mut stderr] = streams.into_inner();
+    let saved = shell.frontend.env_mut().export_temporarily(envs);
+    let result = builtin_command.exec(argv, shell, &mut stdin, &mut stderr, &mut stdout);
+    shell.frontend.env_mut().restore(saved);
+    match (result, signals::interrupted()) {
+        (Ok(exit_status), _) => Ok(exit_status),
+        // Cancelled by an interrupt of the shell, see `Stream`.
+        (Err(_), Some(signal)) => Ok(ExitStatus::signaled(signal, false)),
+        // The reader of the output went away, which ends the builtin and
+        // whatever it runs in quietly, like SIGPIPE ends a process.
+        (Err(err), None) if crate::builtins::is_broken_pipe(err.as_ref()) => {
+            let status = ExitStatus::signaled(Signal::SIGPIPE, false);
+            let code = status.code().unwrap_or(1);
+            shell
+                .frontend
+                .env_mut()
+                .set_control(Some(Control::Exit(code)));
+            Ok(status)
         }
+        (Err(err), None) => {
+            writeln!(stderr, "{}", err).map_err(|_| "failed to write error to stderr")?;
+            Ok(ExitStatus::new(Some(1)))
+        }
+    }
+}
+
+/// The status of a command that failed to start and the message to report:
+/// 127 if it was not found and 126 if it cannot be executed, e.g. because
+/// it lacks the permission or is a directory.
+fn spawn_failure(name: &str, err: &io::Error) -> (ExitStatus, String) {
+    match err.kind() {
+        io::ErrorKind::NotFound if !name.contains('/') => (
+            ExitStatus::new(Some(ExitStatus::NOT_FOUND)),
+            String::from("command not found"),
+        ),
+        io::ErrorKind::NotFound => (
+            ExitStatus::new(Some(ExitStatus::NOT_FOUND)),
+            String::from("No such file or directory"),
+        ),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::IsADirectory => (
+            ExitStatus::new(Some(ExitStatus::NOT_EXECUTABLE)),
+            String::from("Permission denied"),
+        ),
+        _ if err.raw_os_error() == Some(nix::libc::ENOEXEC) => (
+            ExitStatus::new(Some(ExitStatus::NOT_EXECUTABLE)),
+            String::from("cannot execute binary file"),
+        ),
+        _ => (ExitStatus::new(Some(1)), err.to_string()),
     }
 }
 
+/// The status of a subshell, or of a thread with its own copy of the frontend:
+/// the code of a pending `exit`, which ends only the copy, if there is one.
+fn subshell_status(frontend: &Frontend, status: ExitStatus) -> ExitStatus {
+    match frontend.env().control() {
+        Some(Control::Exit(code)) if status.code() != Some(code) => ExitStatus::new(Some(code)),
+        _ => status,
+    }
+}
+
+/// Reports an error of the shell itself on `stderr`.
+fn report(stderr: &Stream, message: impl fmt::Display) {
+    if let Ok(mut stderr) = stderr.try_clone() {
+        let _ = writeln!(stderr, "{message}");
+    }
+}
+
+/// What a loop does after its condition or body ran.
+#[derive(PartialEq)]
+enum Flow {
+    Proceed,
+    Continue,
+    Break,
+}
+
+/// Takes the pending `break` or `continue` for the innermost loop, leaving
+/// what is left of it for the outer loops. An interrupt leaves all loops.
+fn take_control(frontend: &mut Frontend) -> Flow {
+    let (flow, rest) = match frontend.env().control() {
+        None if signals::interrupted().is_some() => (Flow::Break, None),
+        None => (Flow::Proceed, None),
+        Some(Control::Break(levels)) => (
+            Flow::Break,
+            (levels > 1).then(|| Control::Break(levels - 1)),
+        ),
+        Some(Control::Continue(1)) => (Flow::Continue, None),
+        Some(Control::Continue(levels)) => (Flow::Break, Some(Control::Continue(levels - 1))),
+        Some(control @ (Control::Return | Control::Exit(_))) => (Flow::Break, Some(control)),
+    };
+    frontend.env_mut().set_control(rest);
+    flow
+}
+
+/// Describes a compiled pipeline for the job table.
+fn describe(pipe: &PipeCommand) -> String {
+    pipe.commands
+        .iter()
+        .map(|command| match &command.command {
+            Command::Compound(compound) => compound.to_string(),
+            _ => command.argv.join(" "),
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         builtins::{echo::EchoCommand, grep::GrepCommand},
-        ir::{CallCommand, Command},
+        ir::{CallCommand, Command, Redirect},
+    };
+    use std::{
+        env, fs,
+        io::Read,
+        os::fd::{AsFd, AsRawFd},
     };
-    use std::collections::HashMap;
 
     #[test]
     fn test_call_command_stdout() -> Result<(), Box<dyn Error + Send + Sync>> {
@@ -176,13 +973,20 @@ mod tests {
                 envs: HashMap::new(),
                 command: Command::Builtin(Box::<EchoCommand>::default()),
                 argv: vec!["echo".to_string(), test_str.to_string()],
+                redirects: vec![],
             }],
         };
 
         let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
         let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
 
-        let status = backend.exec(command, stdin_reader, stdout_writer)?;
+        let status = backend.exec(
+            command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
         assert!(matches!(status.code(), Some(0)));
 
         let mut stdout_output = String::new();
@@ -204,11 +1008,13 @@ mod tests {
                     envs: HashMap::from([(test_key.to_string(), test_value.to_string())]),
                     command: Command::Call,
                     argv: vec!["env".to_string()],
+                    redirects: vec![],
                 },
                 CallCommand {
                     argv: vec!["grep".into(), "^some_key=".into()],
                     command: Command::Call,
                     envs: HashMap::new(),
+                    redirects: vec![],
                 },
             ],
         };
@@ -216,7 +1022,13 @@ mod tests {
         let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
         let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
 
-        let status = backend.exec(command, stdin_reader, stdout_writer)?;
+        let status = backend.exec(
+            command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
         assert!(matches!(status.code(), Some(0)));
 
         let mut stdout_output = String::new();
@@ -235,13 +1047,20 @@ mod tests {
                 envs: HashMap::new(),
                 command: Command::Call,
                 argv: vec!["sh".to_string(), "-c".to_string(), "exit 5".to_string()],
+                redirects: vec![],
             }],
         };
 
         let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
         let (_stdout_reader, stdout_writer) = os_pipe::pipe()?;
 
-        let status = backend.exec(command, stdin_reader, stdout_writer)?;
+        let status = backend.exec(
+            command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
         assert!(matches!(status.code(), Some(5)));
 
         Ok(())
@@ -255,7 +1074,13 @@ mod tests {
         let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
         let (_stdout_reader, stdout_writer) = os_pipe::pipe()?;
 
-        let status = backend.exec(pipe_command, stdin_reader, stdout_writer)?;
+        let status = backend.exec(
+            pipe_command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
         assert!(matches!(status.code(), Some(0)));
 
         Ok(())
@@ -270,11 +1095,13 @@ mod tests {
                     argv: vec!["echo".into(), "Hello".into()],
                     command: Command::Builtin(Box::<EchoCommand>::default()),
                     envs: HashMap::new(),
+                    redirects: vec![],
                 },
                 CallCommand {
                     argv: vec!["grep".into(), "Hello".into()],
                     command: Command::Builtin(Box::<GrepCommand>::default()),
                     envs: HashMap::new(),
+                    redirects: vec![],
                 },
             ],
         };
@@ -282,7 +1109,13 @@ mod tests {
         let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
         let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
 
-        let status = backend.exec(pipe_command, stdin_reader, stdout_writer)?;
+        let status = backend.exec(
+            pipe_command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
         assert!(matches!(status.code(), Some(0)));
 
         let mut stdout_output = String::new();
@@ -301,16 +1134,19 @@ mod tests {
                     argv: vec!["echo".into(), "Hello World".into()],
                     command: Command::Builtin(Box::<EchoCommand>::default()),
                     envs: HashMap::new(),
+                    redirects: vec![],
                 },
                 CallCommand {
                     argv: vec!["tr".into(), "-d".into(), "o".into()],
                     command: Command::Call,
                     envs: HashMap::new(),
+                    redirects: vec![],
                 },
                 CallCommand {
                     argv: vec!["tr".into(), "-d".into(), "e".into()],
                     command: Command::Call,
                     envs: HashMap::new(),
+                    redirects: vec![],
                 },
             ],
         };
@@ -318,7 +1154,13 @@ mod tests {
         let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
         let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
 
-        let status = backend.exec(pipe_command, stdin_reader, stdout_writer)?;
+        let status = backend.exec(
+            pipe_command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
         assert!(matches!(status.code(), Some(0)));
 
         let mut stdout_output = String::new();
@@ -337,11 +1179,13 @@ mod tests {
                     argv: vec!["false".into()],
                     command: Command::Call,
                     envs: HashMap::new(),
+                    redirects: vec![],
                 },
                 CallCommand {
                     argv: vec!["echo".into(), "Continued".into()],
                     command: Command::Builtin(Box::<EchoCommand>::default()),
                     envs: HashMap::new(),
+                    redirects: vec![],
                 },
             ],
         };
@@ -349,7 +1193,13 @@ mod tests {
         let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
         let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
 
-        let status = backend.exec(pipe_command, stdin_reader, stdout_writer)?;
+        let status = backend.exec(
+            pipe_command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
         assert!(matches!(status.code(), Some(0)));
 
         let mut stdout_output = String::new();
@@ -373,13 +1223,22 @@ mod tests {
                     "Red".to_string(),
                     "-".to_string(),
                 ],
+                redirects: vec![],
             }],
         };
 
         let (stdin_reader, mut stdin_writer) = os_pipe::pipe()?;
         let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
 
-        let status = thread::spawn(move || backend.exec(pipe_command, stdin_reader, stdout_writer));
+        let status = thread::spawn(move || {
+            backend.exec(
+                pipe_command,
+                &mut Frontend::new(),
+                stdin_reader,
+                stdout_writer,
+                Stream::Stderr,
+            )
+        });
 
         write!(
             stdin_writer,
@@ -395,12 +1254,668 @@ mod tests {
 
         assert_eq!(
             stdout_output,
-            format!(
-                "{}",
-                "Red1\nA\nG\n--\nRed2\nE\nF\nRedRed\nRed\nBlueRed\nReed\n"
-            )
+            "Red1\nA\nG\n--\nRed2\nE\nF\nRedRed\nRed\nBlueRed\nReed\n"
+        );
+
+        Ok(())
+    }
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("shell-backend-{}-{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_redirect_output_to_file() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let backend = Backend::new();
+        let path = temp_path("output");
+        for (redirect, text) in [
+            (Redirect::Write(1, path.clone()), "first"),
+            (Redirect::Append(1, path.clone()), "second"),
+        ] {
+            let pipe_command = PipeCommand {
+                commands: vec![CallCommand {
+                    envs: HashMap::new(),
+                    command: Command::Builtin(Box::<EchoCommand>::default()),
+                    argv: vec!["echo".into(), text.into()],
+                    redirects: vec![redirect],
+                }],
+            };
+            let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
+            let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
+            let status = backend.exec(
+                pipe_command,
+                &mut Frontend::new(),
+                stdin_reader,
+                stdout_writer,
+                Stream::Stderr,
+            )?;
+            assert!(matches!(status.code(), Some(0)));
+
+            let mut stdout_output = String::new();
+            stdout_reader.read_to_string(&mut stdout_output)?;
+            assert_eq!(stdout_output, "");
+        }
+
+        assert_eq!(fs::read_to_string(&path)?, "first\nsecond\n");
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_redirect_input_and_stderr() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let backend = Backend::new();
+        let path = temp_path("input");
+        fs::write(&path, "from file\n")?;
+        let pipe_command = PipeCommand {
+            commands: vec![CallCommand {
+                envs: HashMap::new(),
+                command: Command::Call,
+                argv: vec!["sh".into(), "-c".into(), "cat; echo oops >&2".into()],
+                redirects: vec![Redirect::Read(0, path.clone()), Redirect::Dup(2, 1)],
+            }],
+        };
+
+        let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
+        let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
+        let status = backend.exec(
+            pipe_command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
+        assert!(matches!(status.code(), Some(0)));
+
+        let mut stdout_output = String::new();
+        stdout_reader.read_to_string(&mut stdout_output)?;
+        assert_eq!(stdout_output, "from file\noops\n");
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_closed_descriptor_of_external_command() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line("sh -c 'test -e /proc/self/fd/0 || echo closed' <&-")?,
+            "closed\n"
+        );
+        assert_eq!(
+            exec_line("sh -c 'echo hidden >&2 || echo failed' 2>&-")?,
+            "failed\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_stderr_redirect() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let backend = Backend::new();
+        let pipe_command = PipeCommand {
+            commands: vec![CallCommand {
+                envs: HashMap::new(),
+                command: Command::Builtin(Box::<GrepCommand>::default()),
+                argv: vec!["grep".into(), "x".into(), temp_path("missing")],
+                redirects: vec![Redirect::Dup(2, 1)],
+            }],
+        };
+
+        let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
+        let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
+        let status = backend.exec(
+            pipe_command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
+        assert!(matches!(status.code(), Some(2)));
+
+        let mut stdout_output = String::new();
+        stdout_reader.read_to_string(&mut stdout_output)?;
+        assert!(stdout_output.starts_with("grep: "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_redirect_skips_command() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let backend = Backend::new();
+        let pipe_command = PipeCommand {
+            commands: vec![CallCommand {
+                envs: HashMap::new(),
+                command: Command::Builtin(Box::<EchoCommand>::default()),
+                argv: vec!["echo".into(), "unreachable".into()],
+                redirects: vec![Redirect::Read(0, temp_path("missing"))],
+            }],
+        };
+
+        let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
+        let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
+        let status = backend.exec(
+            pipe_command,
+            &mut Frontend::new(),
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
+        assert!(matches!(status.code(), Some(1)));
+
+        let mut stdout_output = String::new();
+        stdout_reader.read_to_string(&mut stdout_output)?;
+        assert_eq!(stdout_output, "");
+        Ok(())
+    }
+
+    fn exec_line(line: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        exec_lines(&[line])
+    }
+
+    /// Runs the lines one after another in the same shell, as if they were read
+    /// from a script.
+    fn exec_lines(lines: &[&str]) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let backend = Backend::new();
+        let mut frontend = Frontend::new();
+        let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
+        for line in lines {
+            let list = frontend.parse(line)?;
+            let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
+            backend.exec_list(
+                list,
+                &mut frontend,
+                stdin_reader,
+                stdout_writer.try_clone()?,
+                Stream::Stderr,
+            )?;
+        }
+        drop(stdout_writer);
+
+        let mut stdout_output = String::new();
+        stdout_reader.read_to_string(&mut stdout_output)?;
+        Ok(stdout_output)
+    }
+
+    #[test]
+    fn test_exported_variables_reach_children() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line("export A=1; B=2; sh -c 'echo $A-$B'; export B; sh -c 'echo $A-$B'")?,
+            "1-\n1-2\n"
+        );
+        assert_eq!(
+            exec_line("export A=1 B=2; unset A; export -n B; sh -c 'echo $A-$B'; echo $B")?,
+            "-\n2\n"
+        );
+        assert_eq!(exec_line("export A=1; A=2; env | grep ^A=")?, "A=2\n");
+        assert_eq!(
+            exec_line("export A='say \"$x\"'; export -p | grep A=")?,
+            "export A=\"say \\\"\\$x\\\"\"\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefix_assignments_are_scoped_to_command() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line("export A=0; A=1 B=2 sh -c 'echo $A$B'; sh -c 'echo $A$B'")?,
+            "12\n0\n"
+        );
+        assert_eq!(
+            exec_line("x=1; x=2 env | grep ^x=; x=3 env; echo $x; env | grep ^x=")?,
+            "x=2\nx=3\n1\n"
         );
+        Ok(())
+    }
 
+    #[test]
+    fn test_env_runs_external_commands() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(exec_line("env A=1 sh -c 'echo $A'")?, "1\n");
+        assert_eq!(exec_line("env true && echo ok")?, "ok\n");
+        assert_eq!(
+            exec_line("export X=1 Y=2; env -u X sh -c 'echo ${X-unset} $Y'")?,
+            "unset 2\n"
+        );
+        assert_eq!(exec_line("export X=1; env -u X | grep -c ^X=")?, "0\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_substitution() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line(r#"echo $(echo a)b "c `echo d` $(echo "$(echo e)")""#)?,
+            "ab c d e\n"
+        );
+        assert_eq!(exec_line("echo x$(printf 'a\\n\\nb\\n\\n')x")?, "xa bx\n");
+        assert_eq!(
+            exec_line("echo \"x$(printf 'a\\n\\nb\\n\\n')x\"")?,
+            "xa\n\nbx\n"
+        );
+        assert_eq!(exec_line("x=1; echo $(x=2; echo $x) $x")?, "2 1\n");
+        assert_eq!(exec_line("echo $(seq 1 20000) | tail -c 6")?, "20000\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_splitting() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line(
+                "x=' a  b\tc '; set -- $x; echo $#; set -- \"$x\"; echo $#; \
+                 set -- $(echo a b c) $(printf 'd\\ne'); echo $# $4 $5; \
+                 e=; set -- $e \"$e\" ${e:-} x$e; echo $#"
+            )?,
+            "3\n1\n5 d e\n2\n"
+        );
+        assert_eq!(
+            exec_line(
+                "IFS=:; x='a::b :c:'; set -- $x; echo $#; printf '[%s]' \"$@\"; echo; \
+                 IFS=; set -- $x; echo $#; unset IFS; y='1 2'; set -- $((1+1))$y; echo $#"
+            )?,
+            "4\n[a][][b ][c]\n1\n2\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_substitution_status() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line(
+                "echo x$(exit 3)y; echo after $?; x=$(exit 4); echo $?; \
+                 echo x$(false) $?; false; y=1; echo $?"
+            )?,
+            "xy\nafter 0\n4\nx 1\n0\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic_expansion() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line("x=3; echo $((x * (2 + 1))) \"$((x += 2))\" $((x++)) $x $(())")?,
+            "9 5 5 6 0\n"
+        );
+        assert_eq!(exec_line("x=1; echo $((x++)) | cat; echo $x")?, "1\n1\n");
+        assert_eq!(exec_line("echo $((1 / 0)) || echo failed")?, "failed\n");
+        assert_eq!(
+            exec_line("echo $((0-5)) $((-2 * 3)); echo -n -1 x; echo")?,
+            "-5 -6\n-1 x\n"
+        );
+        assert_eq!(
+            exec_line("let x=2 'y = x ** 3' && echo $y; let 'x - 2' || echo zero")?,
+            "8\nzero\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_special_parameters() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line("false; echo $?; ! true; echo $?; false || echo $?")?,
+            "1\n1\n1\n"
+        );
+        assert_eq!(
+            exec_line("set -- a 'b c' d; echo $# $1 $@; shift; echo $1 ${#} ${#@}")?,
+            "3 a a b c d\nb c 2 2\n"
+        );
+        assert_eq!(
+            exec_line(r#"set -- 'a b' c; sh -c 'echo $#' x "$@"; sh -c 'echo $#' x "-$@-""#)?,
+            "2\n2\n"
+        );
+        assert_eq!(
+            exec_line(r#"set --; sh -c 'echo $#' x "$@"; shift || echo failed"#)?,
+            "0\nfailed\n"
+        );
+        assert_eq!(exec_line("echo $$")?, format!("{}\n", std::process::id()));
+        assert_ne!(exec_line("sleep 0 & echo $!")?, "\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_pathname_and_brace_expansion() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let dir = std::env::temp_dir().join(format!("shell-glob-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub"))?;
+        for file in ["b.txt", "a.txt", ".hidden.txt", "sub/c.txt", "*.txt"] {
+            std::fs::write(dir.join(file), "")?;
+        }
+        let cd = format!("cd {}", dir.display());
+        assert_eq!(
+            exec_line(&format!("{cd}; echo *.txt; echo ?.t[x]t */*; echo .*.txt"))?,
+            "*.txt a.txt b.txt\n*.txt a.txt b.txt sub/c.txt\n.hidden.txt\n"
+        );
+        assert_eq!(
+            exec_line(&format!(
+                r#"{cd}; p='*'; echo '*'.txt "*".txt \*.txt; echo $p.txt "$p.txt""#
+            ))?,
+            "*.txt *.txt *.txt\n*.txt a.txt b.txt *.txt\n"
+        );
+        assert_eq!(
+            exec_line(&format!(
+                "{cd}; echo no*; set -o nullglob; echo no* x; set -o failglob; echo no* || echo failed"
+            ))?,
+            "no*\nx\nfailed\n"
+        );
+        assert_eq!(
+            exec_line(&format!(
+                "{cd}; echo {{a,b}}.txt {{1..3}}; set -o noglob; echo *.txt"
+            ))?,
+            "a.txt b.txt 1 2 3\n*.txt\n"
+        );
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tilde_expansion() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line("HOME=/h; A=~/bin:~; echo ~ ~/x \"~\" $A; cd /tmp; cd /; echo ~+ ~-")?,
+            "/h /h/x ~ /h/bin:/h\n/ /tmp\n"
+        );
+        assert_eq!(
+            exec_line("HOME=/h; X=~/y sh -c 'echo $X'; echo x > ~nobody-here/f || echo failed")?,
+            "/h/y\nfailed\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_control_flow() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line(
+                "for i in 1 2 3; do for j in a b c; do \
+                   if [ $j = b ]; then continue; elif [ $i = 3 ]; then break 2; fi; \
+                   echo -n $i$j; done; done; echo \" $?\""
+            )?,
+            "1a1c2a2c 0\n"
+        );
+        assert_eq!(
+            exec_line(
+                "n=0; while [ $n -lt 3 ]; do n=$((n+1)); done; echo $n; \
+                 until [ $n = 5 ]; do n=$((n+1)); for x in y; do continue 2; done; echo no; done; echo $n"
+            )?,
+            "3\n5\n"
+        );
+        assert_eq!(
+            exec_line(
+                "case a.txt in *.rs) echo rs;; *.md | *.txt) echo text;; esac; \
+                 case x in y) ;; esac; echo $?; if false; then :; fi; echo $?; \
+                 for i in 1 2; do false; done; echo $?"
+            )?,
+            "text\n0\n0\n1\n"
+        );
+        assert_eq!(
+            exec_line(
+                "set -- a b; for p; do echo $p; done | sort -r; \
+                 { echo 2; echo 1; } | sort; (x=1; cd /); echo \"$x\" $(for i in {1..3}; do echo -n $i; done)"
+            )?,
+            "b\na\n1\n2\n 123\n"
+        );
+        assert_eq!(
+            exec_line("list='x y'; for w in $list \"$list\" $(echo a b); do echo \"[$w]\"; done")?,
+            "[x]\n[y]\n[x y]\n[a]\n[b]\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stderr_of_compound_commands_and_functions() -> Result<(), Box<dyn Error + Send + Sync>>
+    {
+        assert_eq!(
+            exec_line(
+                "{ echo hidden >&2; echo out; } 2>/dev/null; { echo err >&2; } 2>&1; \
+                 f() { echo f >&2; no-such-command-here; }; f 2>&1 | tr a-z A-Z; \
+                 (echo sub >&2) 2>&1; for i in 1; do echo loop >&2; done 2>&1"
+            )?,
+            "out\nerr\nF\nSHELL: NO-SUCH-COMMAND-HERE: COMMAND NOT FOUND\nsub\nloop\n"
+        );
+        Ok(())
+    }
+
+    /// Points the descriptor 2 of the test process to `fd`.
+    fn set_stderr(fd: &impl AsRawFd) -> io::Result<()> {
+        // SAFETY: dup2 only replaces descriptor 2, `fd` stays open for the call.
+        match unsafe { nix::libc::dup2(fd.as_raw_fd(), 2) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    #[test]
+    fn test_external_command_writes_to_shell_stderr() -> Result<(), Box<dyn Error + Send + Sync>> {
+        // Other tests may write to the shell's stderr meanwhile, so only look
+        // for the line of this one.
+        let saved = io::stderr().as_fd().try_clone_to_owned()?;
+        let (mut stderr_reader, stderr_writer) = os_pipe::pipe()?;
+        set_stderr(&stderr_writer)?;
+        drop(stderr_writer);
+        let output = exec_line("sh -c 'echo to-stderr' >&2");
+        set_stderr(&saved)?;
+        assert_eq!(output?, "");
+
+        let mut captured = String::new();
+        let mut buf = [0; 4096];
+        while !captured.contains("to-stderr\n") {
+            let n = stderr_reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            captured.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+        assert!(captured.contains("to-stderr\n"), "{captured:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_functions() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line(
+                "x=global; set -- outer; \
+                 f() { local x=inner; echo $# $1 $x; g; return 3; echo never; }; \
+                 g() { echo g $x; }; f a b; echo $? $x $1"
+            )?,
+            "2 a inner\ng inner\n3 global outer\n"
+        );
+        assert_eq!(
+            exec_line(
+                "first() { for i in 1 2 3; do [ $i = 2 ] && return $i; done; }; first; echo $?; \
+                 echo() { exit 1; }; echo builtin; upper() { tr a-z A-Z; }; echo abc | upper; \
+                 h() { sh -c 'echo $V'; }; V=1 h; echo \"[$V]\"; return; local y"
+            )?,
+            "2\nbuiltin\nABC\n1\n[]\n"
+        );
+        assert_eq!(
+            exec_line("f() { return $1; }; f 300; echo $?; f -1; echo $?")?,
+            "44\n255\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_exit_ends_only_subshells() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(exec_line("(exit 3); echo $?")?, "3\n");
+        assert_eq!(
+            exec_line("(false; exit); echo $?; (exit); echo $?")?,
+            "1\n0\n"
+        );
+        assert_eq!(
+            exec_line(
+                "(exit 300); echo $?; echo | { exit 4; echo never; }; echo $?; \
+                 echo | for i in 1 2; do exit 5; done; echo $?; exit 6; echo never"
+            )?,
+            "44\n4\n5\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_signals_and_broken_pipes() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line(
+                "sh -c 'kill -TERM $$'; echo $?; \
+                 while true; do echo y; done | head -2; echo $?"
+            )?,
+            "143\ny\ny\n0\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_exit_status_of_failed_commands() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_line(
+                "no-such-command-here; echo $?; /; echo $?; ! /no/such/file && echo negated"
+            )?,
+            "127\n126\nnegated\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_aliases() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(
+            exec_lines(&[
+                "alias say='echo ' hi=hello two='say a; say b'",
+                "say hi there; two c; alias hi; unalias hi",
+                "say hi; unalias -a",
+                "alias",
+            ])?,
+            "hello there\na\nb c\nalias hi=\"hello\"\nhi\n"
+        );
+        // Aliases apply to the lines read after they are defined or removed.
+        assert_eq!(
+            exec_lines(&[
+                "alias x='echo alias'; x 2>/dev/null || echo not yet",
+                "f() { x; }; unalias x; echo $(x) sub; f",
+            ])?,
+            "not yet\nalias sub\nalias\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parameter_expansion() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let f = "f=dir/sub/file.tar.gz; e=";
+        assert_eq!(
+            exec_line(&format!(
+                "{f}; echo ${{f#*/}} ${{f##*/}} ${{f%.*}} ${{f%%.*}} ${{#f}}"
+            ))?,
+            "sub/file.tar.gz file.tar.gz dir/sub/file.tar dir/sub/file 19\n"
+        );
+        assert_eq!(
+            exec_line(&format!(
+                "{f}; echo ${{u:-d}}${{e:-d}}${{e-d}}${{u-d}}-${{e+s}}${{u+s}}"
+            ))?,
+            "ddd-s\n"
+        );
+        assert_eq!(
+            exec_line("echo ${u:=a}; echo $u; echo ${v:?is required} || echo failed")?,
+            "a\na\n"
+        );
+        assert_eq!(
+            exec_line("(echo ${v:?}); echo $?; for i in ${v?}; do :; done; echo after")?,
+            "1\n"
+        );
+        assert_eq!(
+            exec_line(&format!(
+                r#"{f}; echo ${{f/sub/S}} ${{f//\//_}} ${{f/#dir/D}} ${{f/%gz/$(echo X)}}"#
+            ))?,
+            "dir/S/file.tar.gz dir_sub_file.tar.gz D/sub/file.tar.gz dir/sub/file.tar.X\n"
+        );
+        assert_eq!(
+            exec_line(&format!(
+                r#"{f}; p='*.gz'; echo ${{f%$p}} ${{f%"$p"}} "${{f/"*"/x}}""#
+            ))?,
+            "dir/sub/file.tar dir/sub/file.tar.gz dir/sub/file.tar.gz\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parameter_error_keeps_interactive_shell() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let backend = Backend::new();
+        let mut frontend = Frontend::new();
+        frontend.env_mut().set_interactive(true);
+        let list = frontend.parse("echo ${v:?is required} || echo failed")?;
+        let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
+        let (mut stdout_reader, stdout_writer) = os_pipe::pipe()?;
+        backend.exec_list(
+            list,
+            &mut frontend,
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
+
+        let mut stdout_output = String::new();
+        stdout_reader.read_to_string(&mut stdout_output)?;
+        assert_eq!(stdout_output, "failed\n");
+        assert_eq!(frontend.env().control(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_runs_every_statement() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(exec_line("echo a; echo b\necho c")?, "a\nb\nc\n");
+        assert_eq!(exec_line("x=1; echo $x; x=2; echo $x")?, "1\n2\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_short_circuits() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(exec_line("false && echo no || echo yes")?, "yes\n");
+        assert_eq!(exec_line("true || echo no && echo yes")?, "yes\n");
+        assert_eq!(exec_line("! false && echo negated")?, "negated\n");
+        assert_eq!(exec_line("false && x=1; echo \"[$x]\"")?, "[]\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_returns_last_status() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let backend = Backend::new();
+        let mut frontend = Frontend::new();
+        for (line, code) in [("true; false", 1), ("false; true", 0), ("false || true", 0)] {
+            let list = frontend.parse(line)?;
+            let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
+            let (_stdout_reader, stdout_writer) = os_pipe::pipe()?;
+            let status = backend.exec_list(
+                list,
+                &mut frontend,
+                stdin_reader,
+                stdout_writer,
+                Stream::Stderr,
+            )?;
+            assert_eq!(status.code(), Some(code));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_background_jobs() -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_eq!(exec_line("echo a & wait")?, "a\n");
+        assert_eq!(
+            exec_line("false && echo no || echo yes & wait %1")?,
+            "yes\n"
+        );
+        assert_eq!(
+            exec_line("sleep 5 & jobs; kill %1; wait; jobs")?,
+            "[1]+  Running                 sleep 5 &\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_returns_job_status() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let backend = Backend::new();
+        let mut frontend = Frontend::new();
+        let list = frontend.parse("sh -c 'exit 3' & sh -c 'exit 4' & wait %1")?;
+        let (stdin_reader, _stdin_writer) = os_pipe::pipe()?;
+        let (_stdout_reader, stdout_writer) = os_pipe::pipe()?;
+        let status = backend.exec_list(
+            list,
+            &mut frontend,
+            stdin_reader,
+            stdout_writer,
+            Stream::Stderr,
+        )?;
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(backend.jobs().ids(), vec![2]);
         Ok(())
     }
 }
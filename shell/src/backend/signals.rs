@@ -0,0 +1,128 @@
+//! SIGINT and SIGQUIT handling: the signals interrupt the foreground job
+//! instead of the shell.
+
+use std::{
+    io,
+    os::fd::{BorrowedFd, IntoRawFd},
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+use nix::{
+    errno::Errno,
+    libc::{self, c_int},
+    poll::{self, PollFd, PollFlags, PollTimeout},
+    sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal},
+    unistd::Pid,
+};
+
+/// Signals that interrupt the foreground job.
+const INTERRUPT_SIGNALS: [Signal; 2] = [Signal::SIGINT, Signal::SIGQUIT];
+
+/// The last interrupt signal received, or 0.
+static INTERRUPTED: AtomicI32 = AtomicI32::new(0);
+/// Process group of the foreground job with job control, or 0.
+static FOREGROUND: AtomicI32 = AtomicI32::new(0);
+/// Ends of a pipe that holds a byte while an interrupt is recorded, so that
+/// reads of builtins can wait for it together with their input. -1 until
+/// [`install`].
+static WAKE_READER: AtomicI32 = AtomicI32::new(-1);
+static WAKE_WRITER: AtomicI32 = AtomicI32::new(-1);
+
+/// Records `signal`, and writes the byte to the wake pipe if no interrupt was
+/// recorded yet.
+fn record(signal: c_int) {
+    let writer = WAKE_WRITER.load(Ordering::SeqCst);
+    if INTERRUPTED.swap(signal, Ordering::SeqCst) == 0 && writer >= 0 {
+        // SAFETY: write is async-signal-safe and the buffer outlives the call.
+        unsafe { libc::write(writer, [0u8].as_ptr().cast(), 1) };
+    }
+}
+
+extern "C" fn handle(signal: c_int) {
+    record(signal);
+    let pgid = FOREGROUND.load(Ordering::SeqCst);
+    if let (true, Ok(signal)) = (pgid > 0, Signal::try_from(signal)) {
+        let _ = signal::killpg(Pid::from_raw(pgid), signal);
+    }
+}
+
+/// Catches SIGINT and SIGQUIT, recording them and forwarding them to the
+/// foreground job.
+///
+/// Caught signals get their default action back in `exec`, so commands started
+/// by the shell are still interrupted by them. Interrupted system calls of the
+/// shell are restarted, reads of builtins wait with [`wait_readable`] instead.
+pub fn install() {
+    if let Ok((reader, writer)) = os_pipe::pipe() {
+        WAKE_READER.store(reader.into_raw_fd(), Ordering::SeqCst);
+        WAKE_WRITER.store(writer.into_raw_fd(), Ordering::SeqCst);
+    }
+    let action = SigAction::new(
+        SigHandler::Handler(handle),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    for signal in INTERRUPT_SIGNALS {
+        // SAFETY: the handler only uses atomics and killpg, which are async-signal-safe.
+        unsafe {
+            let _ = signal::sigaction(signal, &action);
+        }
+    }
+}
+
+/// The interrupt signal received since the last [`clear`], if any.
+pub fn interrupted() -> Option<Signal> {
+    match INTERRUPTED.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Signal::try_from(signal).ok(),
+    }
+}
+
+/// Records an interrupt, e.g. of a foreground job that was killed by one
+/// sent to its own process group.
+pub fn interrupt(signal: Signal) {
+    record(signal as c_int);
+}
+
+pub fn clear() {
+    let reader = WAKE_READER.load(Ordering::SeqCst);
+    if INTERRUPTED.swap(0, Ordering::SeqCst) != 0 && reader >= 0 {
+        // SAFETY: the byte written by `record` is read into a local buffer.
+        unsafe { libc::read(reader, [0u8].as_mut_ptr().cast(), 1) };
+    }
+}
+
+/// Waits until `fd` is ready to be read or an interrupt is recorded, whichever
+/// comes first. Returns right away before [`install`].
+pub fn wait_readable(fd: BorrowedFd) -> io::Result<()> {
+    let wake = WAKE_READER.load(Ordering::SeqCst);
+    if wake < 0 {
+        return Ok(());
+    }
+    // SAFETY: the wake pipe is never closed once installed.
+    let wake = unsafe { BorrowedFd::borrow_raw(wake) };
+    loop {
+        let mut fds = [
+            PollFd::new(fd, PollFlags::POLLIN),
+            PollFd::new(wake, PollFlags::POLLIN),
+        ];
+        match poll::poll(&mut fds, PollTimeout::NONE) {
+            Ok(_) => {}
+            Err(Errno::EINTR) => continue,
+            Err(err) => return Err(err.into()),
+        }
+        if interrupted().is_some() || fds[0].any().unwrap_or(true) {
+            return Ok(());
+        }
+    }
+}
+
+/// Sets the process group that interrupts are forwarded to.
+pub fn set_foreground(pgid: Option<Pid>) {
+    FOREGROUND.store(pgid.map_or(0, Pid::as_raw), Ordering::SeqCst);
+}
+
+/// Whether `signal` is one that interrupts the foreground job.
+pub fn is_interrupt(signal: Signal) -> bool {
+    INTERRUPT_SIGNALS.contains(&signal)
+}
@@ -0,0 +1,229 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    os::fd::{AsFd, RawFd},
+    path::Path,
+    process::Stdio,
+};
+
+use os_pipe::{PipeReader, PipeWriter};
+
+use super::signals;
+use crate::ir::Redirect;
+
+/// A stream that a standard descriptor of a command is connected to.
+#[derive(Debug)]
+pub enum Stream {
+    Reader(PipeReader),
+    Writer(PipeWriter),
+    File(File),
+    /// The standard error of the shell itself.
+    Stderr,
+    /// A descriptor closed with `>&-`.
+    Closed,
+}
+
+impl Stream {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Stream::Reader(reader) => Stream::Reader(reader.try_clone()?),
+            Stream::Writer(writer) => Stream::Writer(writer.try_clone()?),
+            Stream::File(file) => Stream::File(file.try_clone()?),
+            Stream::Stderr => Stream::Stderr,
+            Stream::Closed => Stream::Closed,
+        })
+    }
+}
+
+impl From<PipeReader> for Stream {
+    fn from(reader: PipeReader) -> Self {
+        Stream::Reader(reader)
+    }
+}
+
+impl From<PipeWriter> for Stream {
+    fn from(writer: PipeWriter) -> Self {
+        Stream::Writer(writer)
+    }
+}
+
+impl From<File> for Stream {
+    fn from(file: File) -> Self {
+        Stream::File(file)
+    }
+}
+
+impl TryFrom<Stream> for Stdio {
+    type Error = io::Error;
+
+    fn try_from(stream: Stream) -> io::Result<Self> {
+        Ok(match stream {
+            Stream::Reader(reader) => reader.into(),
+            Stream::Writer(writer) => writer.into(),
+            Stream::File(file) => file.into(),
+            // A duplicate rather than `Stdio::inherit()`, which would give the
+            // shell's own descriptor of the slot, e.g. its stdout for `>&2`.
+            Stream::Stderr => io::stderr().as_fd().try_clone_to_owned()?.into(),
+            // Only a placeholder, the descriptor is closed before `exec`, see
+            // `StdStreams::closed`.
+            Stream::Closed => Stdio::null(),
+        })
+    }
+}
+
+fn bad_descriptor() -> io::Error {
+    io::Error::other("Bad file descriptor")
+}
+
+/// Fails once the shell is interrupted, which cancels builtins that read or
+/// write. Not `ErrorKind::Interrupted`, since that is retried.
+fn check_interrupted() -> io::Result<()> {
+    match signals::interrupted() {
+        Some(signal) => Err(io::Error::other(format!("interrupted by {signal}"))),
+        None => Ok(()),
+    }
+}
+
+impl Read for Stream {
+    /// Waits for input together with interrupts, so that a blocked builtin is
+    /// cancelled by them, even on another thread than the one handling them.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        check_interrupted()?;
+        match self {
+            Stream::Reader(reader) => {
+                signals::wait_readable(reader.as_fd())?;
+                check_interrupted()?;
+                reader.read(buf)
+            }
+            Stream::File(file) => {
+                signals::wait_readable(file.as_fd())?;
+                check_interrupted()?;
+                file.read(buf)
+            }
+            _ => Err(bad_descriptor()),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !matches!(self, Stream::Stderr) {
+            check_interrupted()?;
+        }
+        match self {
+            Stream::Writer(writer) => writer.write(buf),
+            Stream::File(file) => file.write(buf),
+            Stream::Stderr => io::stderr().write(buf),
+            _ => Err(bad_descriptor()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Writer(writer) => writer.flush(),
+            Stream::File(file) => file.flush(),
+            Stream::Stderr => io::stderr().flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Standard input, output and error of a command, indexed by descriptor.
+pub struct StdStreams([Stream; 3]);
+
+impl StdStreams {
+    pub fn new(stdin: Stream, stdout: Stream, stderr: Stream) -> Self {
+        Self([stdin, stdout, stderr])
+    }
+
+    /// Applies redirections from left to right, so that `>out 2>&1` and
+    /// `2>&1 >out` behave differently, just as in POSIX shells. Relative paths
+    /// are opened in `cwd`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message suitable for the user if a file cannot be opened or
+    /// a descriptor other than 0, 1 or 2 is redirected.
+    pub fn apply(&mut self, redirects: &[Redirect], cwd: &Path) -> Result<(), String> {
+        for redirect in redirects {
+            let (fd, stream) = match redirect {
+                Redirect::Read(fd, path) => {
+                    (fd, Self::open(cwd, path, OpenOptions::new().read(true))?)
+                }
+                Redirect::Write(fd, path) => (
+                    fd,
+                    Self::open(
+                        cwd,
+                        path,
+                        OpenOptions::new().write(true).create(true).truncate(true),
+                    )?,
+                ),
+                Redirect::Append(fd, path) => (
+                    fd,
+                    Self::open(cwd, path, OpenOptions::new().append(true).create(true))?,
+                ),
+                Redirect::ReadWrite(fd, path) => (
+                    fd,
+                    Self::open(
+                        cwd,
+                        path,
+                        OpenOptions::new().read(true).write(true).create(true),
+                    )?,
+                ),
+                Redirect::Dup(fd, target) => {
+                    let stream = self
+                        .get(*target)?
+                        .try_clone()
+                        .map_err(|err| format!("{target}: {err}"))?;
+                    (fd, stream)
+                }
+                Redirect::Close(fd) => (fd, Stream::Closed),
+            };
+            *self.get_mut(*fd)? = stream;
+        }
+        Ok(())
+    }
+
+    fn open(cwd: &Path, path: &str, options: &OpenOptions) -> Result<Stream, String> {
+        options
+            .open(cwd.join(path))
+            .map(Stream::File)
+            .map_err(|err| format!("{path}: {err}"))
+    }
+
+    fn get(&self, fd: u16) -> Result<&Stream, String> {
+        self.0
+            .get(fd as usize)
+            .ok_or_else(|| format!("{fd}: redirection of this descriptor is not supported"))
+    }
+
+    fn get_mut(&mut self, fd: u16) -> Result<&mut Stream, String> {
+        self.0
+            .get_mut(fd as usize)
+            .ok_or_else(|| format!("{fd}: redirection of this descriptor is not supported"))
+    }
+
+    /// The descriptors closed with `>&-`. An external command gets them closed
+    /// in the child process, as `Stdio` cannot leave a descriptor closed.
+    pub fn closed(&self) -> Vec<RawFd> {
+        (0..)
+            .zip(&self.0)
+            .filter(|(_, stream)| matches!(stream, Stream::Closed))
+            .map(|(fd, _)| fd)
+            .collect()
+    }
+
+    pub fn stderr(&self) -> &Stream {
+        &self.0[2]
+    }
+
+    /// Converts the streams for an external command.
+    pub fn into_stdio(self) -> io::Result<[Stdio; 3]> {
+        let [stdin, stdout, stderr] = self.0;
+        Ok([stdin.try_into()?, stdout.try_into()?, stderr.try_into()?])
+    }
+
+    pub fn into_inner(self) -> [Stream; 3] {
+        self.0
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    frontend::Environment,
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+/// Characters that cannot appear in alias names.
+const RESERVED: &str = " \t\n/$`'\"\\=|&;()<>";
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Print every alias in a form that can be read back by the shell.
+    #[arg(short = 'p')]
+    print: bool,
+
+    /// Aliases to define as `name=value`, or to print as `name`.
+    definitions: Vec<String>,
+}
+
+/// Implements the alias built-in command.
+///
+/// AliasCommand defines aliases, which replace the first word of commands,
+/// and prints them.
+#[derive(Default, Debug)]
+pub struct AliasCommand;
+
+impl BuiltinCommand for AliasCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        stderr: &mut dyn std::io::Write,
+        stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let env = shell.frontend.env_mut();
+
+        if args.print || args.definitions.is_empty() {
+            for (name, value) in env.aliases() {
+                writeln!(stdout, "alias {name}={}", Environment::quote(value))?;
+            }
+        }
+
+        let mut code = 0;
+        for definition in args.definitions {
+            let Some((name, value)) = definition.split_once('=') else {
+                match env.alias(&definition) {
+                    Some(value) => {
+                        writeln!(stdout, "alias {definition}={}", Environment::quote(value))?
+                    }
+                    None => {
+                        writeln!(stderr, "alias: {definition}: not found")?;
+                        code = 1;
+                    }
+                }
+                continue;
+            };
+            if name.is_empty() || name.contains(|c| RESERVED.contains(c)) {
+                writeln!(stderr, "alias: `{name}': invalid alias name")?;
+                code = 1;
+                continue;
+            }
+            env.set_alias(name, value);
+        }
+        Ok(ExitStatus::new(Some(code)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "alias"
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    jobspecs: Vec<String>,
+}
+
+/// Implements the bg built-in command.
+///
+/// BgCommand continues stopped jobs, the current one by default, in the background.
+#[derive(Default, Debug)]
+pub struct BgCommand;
+
+impl BuiltinCommand for BgCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let mut args = Args::try_parse_from(args)?;
+        if args.jobspecs.is_empty() {
+            args.jobspecs.push(String::from("%%"));
+        }
+
+        for spec in &args.jobspecs {
+            let id = shell
+                .backend
+                .jobs()
+                .find(spec)
+                .map_err(|_| format!("bg: {spec}: no such job"))?;
+            let command = shell
+                .backend
+                .background(id)
+                .map_err(|err| format!("bg: {err}"))?;
+            writeln!(stdout, "[{id}] {command} &")?;
+        }
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "bg"
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    frontend::Control,
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Number of enclosing loops to leave.
+    #[arg(default_value_t = 1)]
+    n: usize,
+}
+
+/// Implements the break built-in command.
+///
+/// BreakCommand leaves the innermost `for`, `while` or `until` loop, or as
+/// many enclosing loops as given, but at most all of them.
+#[derive(Default, Debug)]
+pub struct BreakCommand;
+
+impl BuiltinCommand for BreakCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        if args.n == 0 {
+            return Err(format!("break: {}: loop count out of range", args.n).into());
+        }
+        let env = shell.frontend.env_mut();
+        if env.loop_depth() == 0 {
+            writeln!(stderr, "break: only meaningful in a loop")?;
+            return Ok(ExitStatus::new(Some(0)));
+        }
+        let levels = args.n.min(env.loop_depth());
+        env.set_control(Some(Control::Break(levels)));
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "break"
+    }
+}
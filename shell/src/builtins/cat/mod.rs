@@ -1,4 +1,7 @@
-use crate::ir::BuiltinCommand;
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
 use clap::Parser;
 use std::{
     error::Error,
@@ -22,11 +25,12 @@ impl BuiltinCommand for CatCommand {
     fn exec(
         &self,
         args: Vec<String>,
+        shell: &mut Shell,
         stdin: &mut dyn Read,
         _stderr: &mut dyn Write,
         stdout: &mut dyn Write,
-    ) -> Result<(), Box<dyn Error + Sync + Send>> {
-        let args = Args::try_parse_from(args.into_iter())?;
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
 
         let mut buf = String::default();
 
@@ -35,13 +39,17 @@ impl BuiltinCommand for CatCommand {
                 stdin.read_to_string(&mut buf)?;
             }
             Some(path) => {
-                let mut file = File::open(path)?;
+                let mut file = File::open(shell.resolve(path))?;
                 file.read_to_string(&mut buf)?;
             }
         };
 
         write!(stdout, "{}", buf)?;
-        Ok(())
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
     }
 
     fn tag(&self) -> &'static str {
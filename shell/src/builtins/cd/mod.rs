@@ -0,0 +1,205 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::{
+    error::Error,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Keep symbolic links in the new working directory (default).
+    #[arg(short = 'L', overrides_with = "physical")]
+    logical: bool,
+
+    /// Resolve symbolic links in the new working directory.
+    #[arg(short = 'P', overrides_with = "logical")]
+    physical: bool,
+
+    dir: Option<String>,
+}
+
+/// Implements the cd built-in command.
+///
+/// CdCommand changes the working directory of the shell, copied to `PWD`,
+/// and keeps the previous one in `OLDPWD`. Without an argument it changes
+/// to `HOME`, `cd -` changes back to `OLDPWD`. Relative directories are
+/// also looked up in the directories listed in `CDPATH`.
+#[derive(Default, Debug)]
+pub struct CdCommand;
+
+impl CdCommand {
+    /// Finds the directory to change to, and whether it was found through `CDPATH`.
+    fn search(dir: &str, current: &Path, cdpath: &str) -> (PathBuf, bool) {
+        let path = Path::new(dir);
+        let is_explicit = path.is_absolute()
+            || matches!(
+                path.components().next(),
+                Some(Component::CurDir | Component::ParentDir)
+            );
+        if !is_explicit {
+            for entry in cdpath.split(':').filter(|_| !cdpath.is_empty()) {
+                let candidate = current.join(entry).join(path);
+                if candidate.is_dir() {
+                    return (candidate, !entry.is_empty());
+                }
+            }
+        }
+        (current.join(path), false)
+    }
+
+    /// Removes `.` and `..` components without resolving symbolic links.
+    fn normalize(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    }
+
+    fn describe(err: io::Error) -> String {
+        let message = err.to_string();
+        match message.find(" (os error") {
+            Some(end) => message[..end].to_string(),
+            None => message,
+        }
+    }
+}
+
+impl BuiltinCommand for CdCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let env = shell.frontend.env();
+        let (dir, print) = match args.dir.as_deref() {
+            None => (env.get("HOME"), false),
+            Some("-") => (env.get("OLDPWD"), true),
+            Some(dir) => (dir.to_string(), false),
+        };
+        if dir.is_empty() {
+            let variable = if print { "OLDPWD" } else { "HOME" };
+            return Err(format!("cd: {variable} not set").into());
+        }
+
+        let current = shell.current_dir();
+        let (path, from_cdpath) = Self::search(&dir, &current, &env.get("CDPATH"));
+        let new_dir = if args.physical {
+            fs::canonicalize(&path).map_err(|err| format!("cd: {dir}: {}", Self::describe(err)))?
+        } else {
+            Self::normalize(&path)
+        };
+        let metadata =
+            fs::metadata(&new_dir).map_err(|err| format!("cd: {dir}: {}", Self::describe(err)))?;
+        if !metadata.is_dir() {
+            return Err(format!("cd: {dir}: Not a directory").into());
+        }
+
+        let env = shell.frontend.env_mut();
+        env.set("OLDPWD", current.to_string_lossy().into_owned());
+        env.set_current_dir(new_dir.clone());
+        if print || from_cdpath {
+            writeln!(stdout, "{}", new_dir.display())?;
+        }
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "cd"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::{
+        backend::{Backend, Stream},
+        frontend::Frontend,
+    };
+
+    fn run(frontend: &mut Frontend, line: &str) -> String {
+        let list = frontend.parse(line).unwrap();
+        let (stdin_reader, _stdin_writer) = os_pipe::pipe().unwrap();
+        let (mut stdout_reader, stdout_writer) = os_pipe::pipe().unwrap();
+        Backend::new()
+            .exec_list(list, frontend, stdin_reader, stdout_writer, Stream::Stderr)
+            .unwrap();
+
+        let mut stdout = String::new();
+        io::Read::read_to_string(&mut stdout_reader, &mut stdout).unwrap();
+        stdout
+    }
+
+    #[test]
+    fn test_cd_changes_shell_directory() {
+        let root = env::temp_dir().join(format!("shell-cd-{}", std::process::id()));
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/file"), "").unwrap();
+        let root_str = root.to_str().unwrap();
+
+        let mut frontend = Frontend::new();
+        assert_eq!(
+            run(&mut frontend, &format!("cd {root_str}/a; pwd")),
+            format!("{root_str}/a\n")
+        );
+        assert_eq!(
+            run(&mut frontend, "cd b/..; cd ./b && pwd"),
+            format!("{root_str}/a/b\n")
+        );
+        assert_eq!(run(&mut frontend, "cd -"), format!("{root_str}/a\n"));
+        assert_eq!(frontend.env().get("OLDPWD"), format!("{root_str}/a/b"));
+        assert_eq!(
+            run(&mut frontend, "echo hi > out; cat out && sh -c pwd"),
+            format!("hi\n{root_str}/a\n")
+        );
+
+        assert_eq!(
+            run(&mut frontend, "cd file || cd missing || pwd"),
+            format!("{root_str}/a\n")
+        );
+        assert_eq!(
+            run(&mut frontend, "cd .. | pwd; cd .. & wait; pwd"),
+            format!("{root_str}/a\n{root_str}/a\n")
+        );
+
+        assert_eq!(
+            run(
+                &mut frontend,
+                "unset PWD; pwd; PWD=/; echo there > out; cat out && sh -c pwd"
+            ),
+            format!("{root_str}/a\nthere\n{root_str}/a\n")
+        );
+        assert_eq!(fs::read_to_string(root.join("a/out")).unwrap(), "there\n");
+
+        frontend.env_mut().set("CDPATH", format!(":{root_str}/a"));
+        assert_eq!(
+            run(&mut frontend, "cd /; cd b"),
+            format!("{root_str}/a/b\n")
+        );
+        frontend.env_mut().set("HOME", root_str.to_string());
+        assert_eq!(run(&mut frontend, "cd; pwd"), format!("{root_str}\n"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
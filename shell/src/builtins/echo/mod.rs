@@ -1,11 +1,16 @@
 use std::error::Error;
 
-use crate::ir::BuiltinCommand;
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
 use clap::Parser;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// Words to print, which may start with a hyphen, like negative numbers.
+    #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
     content: Vec<String>,
 
     #[arg(short = 'n')]
@@ -22,15 +27,20 @@ impl BuiltinCommand for EchoCommand {
     fn exec(
         &self,
         args: Vec<String>,
+        _shell: &mut Shell,
         _stdin: &mut dyn std::io::Read,
         _stderr: &mut dyn std::io::Write,
         stdout: &mut dyn std::io::Write,
-    ) -> Result<(), Box<dyn Error + Sync + Send>> {
-        let args = Args::try_parse_from(args.into_iter())?;
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
         let mut output = args.content.join(" ");
         (!args.remove_trailing_newline).then(|| output += "\n");
         write!(stdout, "{}", output)?;
-        Ok(())
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
     }
 
     fn tag(&self) -> &'static str {
@@ -0,0 +1,74 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Start with an empty environment.
+    #[arg(short = 'i', long = "ignore-environment")]
+    ignore_environment: bool,
+
+    /// Variables to add, written as `name=value`.
+    assignments: Vec<String>,
+}
+
+/// Implements the env built-in command.
+///
+/// EnvCommand prints the environment passed to executed commands, one
+/// `name=value` pair per line. Other uses, like running a command or `-u`,
+/// are left to the external `env`.
+#[derive(Default, Debug)]
+pub struct EnvCommand;
+
+impl BuiltinCommand for EnvCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let mut env = match args.ignore_environment {
+            true => Default::default(),
+            false => shell.frontend.env().exported(),
+        };
+        for assignment in args.assignments {
+            let Some((name, value)) = assignment.split_once('=') else {
+                return Err(format!("env: {assignment}: running commands is not supported").into());
+            };
+            env.insert(name.to_string(), value.to_string());
+        }
+
+        let mut env: Vec<_> = env.into_iter().collect();
+        env.sort();
+        for (name, value) in env {
+            writeln!(stdout, "{name}={value}")?;
+        }
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn handles(&self, args: &[String]) -> bool {
+        args.iter()
+            .all(|arg| matches!(arg.as_str(), "-i" | "--ignore-environment") || is_assignment(arg))
+    }
+
+    fn tag(&self) -> &'static str {
+        "env"
+    }
+}
+
+/// Whether `arg` is a `name=value` operand rather than an option or a command.
+fn is_assignment(arg: &str) -> bool {
+    arg.split_once('=')
+        .is_some_and(|(name, _)| !name.is_empty() && !name.starts_with('-'))
+}
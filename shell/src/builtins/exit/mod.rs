@@ -1,17 +1,22 @@
-use crate::ir::BuiltinCommand;
+use crate::{
+    backend::{ExitStatus, Shell},
+    frontend::Control,
+    ir::BuiltinCommand,
+};
 use clap::Parser;
-use std::{error::Error, process::exit};
+use std::error::Error;
 
 #[derive(Parser, Debug, Default)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// Exit status, that of the last command by default.
     code: Option<i32>,
 }
 
 /// Implements the exit built-in command.
 ///
-/// ExitCommand terminates the shell optional exit status code.
-/// If no code is provided, it defaults to exiting with status 0.
+/// ExitCommand terminates the shell, or the subshell it runs in, with an optional
+/// exit status code. If no code is provided, the status of the last command is used.
 #[derive(Default, Debug)]
 pub struct ExitCommand;
 
@@ -19,12 +24,25 @@ impl BuiltinCommand for ExitCommand {
     fn exec(
         &self,
         args: Vec<String>,
+        shell: &mut Shell,
         _stdin: &mut dyn std::io::Read,
         _stderr: &mut dyn std::io::Write,
         _stdout: &mut dyn std::io::Write,
-    ) -> Result<(), Box<dyn Error + Sync + Send>> {
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
         let args = Args::try_parse_from(args).unwrap_or_default();
-        exit(args.code.unwrap_or_default())
+        let code = args
+            .code
+            .unwrap_or(shell.frontend.env().status())
+            .rem_euclid(256);
+        shell
+            .frontend
+            .env_mut()
+            .set_control(Some(Control::Exit(code)));
+        Ok(ExitStatus::new(Some(code)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
     }
 
     fn tag(&self) -> &'static str {
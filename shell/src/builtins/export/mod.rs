@@ -0,0 +1,82 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    frontend::Environment,
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Print exported variables in a form that can be read back by the shell.
+    #[arg(short = 'p')]
+    print: bool,
+
+    /// Stop exporting the given variables.
+    #[arg(short = 'n')]
+    unexport: bool,
+
+    /// Variables to export, written as `name` or `name=value`.
+    names: Vec<String>,
+}
+
+/// Implements the export built-in command.
+///
+/// ExportCommand marks shell variables to be passed to the environment of
+/// executed commands, optionally assigning them a value.
+#[derive(Default, Debug)]
+pub struct ExportCommand;
+
+impl BuiltinCommand for ExportCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        stderr: &mut dyn std::io::Write,
+        stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let env = shell.frontend.env_mut();
+
+        if args.names.is_empty() {
+            let mut exported: Vec<_> = env.exported().into_iter().collect();
+            exported.sort();
+            for (name, value) in exported {
+                writeln!(stdout, "export {name}={}", Environment::quote(&value))?;
+            }
+            return Ok(ExitStatus::new(Some(0)));
+        }
+
+        let mut code = 0;
+        for arg in args.names {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if !Environment::is_valid_name(name) {
+                writeln!(stderr, "export: `{arg}': not a valid identifier")?;
+                code = 1;
+                continue;
+            }
+            if args.unexport {
+                if let Some(value) = value {
+                    env.set(name, value);
+                }
+                env.unexport(name);
+            } else {
+                env.export(name, value);
+            }
+        }
+        Ok(ExitStatus::new(Some(code)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "export"
+    }
+}
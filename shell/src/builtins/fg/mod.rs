@@ -0,0 +1,51 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    jobspec: Option<String>,
+}
+
+/// Implements the fg built-in command.
+///
+/// FgCommand continues a job, the current one by default, in the foreground
+/// and waits for it.
+#[derive(Default, Debug)]
+pub struct FgCommand;
+
+impl BuiltinCommand for FgCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let spec = args.jobspec.as_deref().unwrap_or("%%");
+        let id = shell
+            .backend
+            .jobs()
+            .find(spec)
+            .map_err(|_| format!("fg: {spec}: no such job"))?;
+        let status = shell
+            .backend
+            .foreground(id)
+            .map_err(|err| format!("fg: {err}"))?;
+        Ok(status)
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "fg"
+    }
+}
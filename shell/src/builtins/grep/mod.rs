@@ -1,22 +1,45 @@
 use std::{
-    collections::HashMap,
+    collections::VecDeque,
     error::Error,
     fs::File,
     io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
 };
 
-use regex::{RegexSet, RegexSetBuilder};
+use crate::{
+    backend::{ExitStatus, Shell},
+    builtins::is_broken_pipe,
+    ir::BuiltinCommand,
+};
+use clap::{ArgAction, ArgGroup, Parser, ValueEnum};
+use pattern::{Matcher, PatternSyntax};
+use printer::Printer;
+use walk::{FileFilter, Walker};
 
-use crate::ir::BuiltinCommand;
-use clap::Parser;
+mod pattern;
+mod printer;
+mod walk;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, disable_help_flag = true)]
+#[command(group(ArgGroup::new("syntax").args(["extended_regexp", "fixed_strings", "basic_regexp"])))]
 struct Args {
     patterns: String,
 
     files: Vec<String>,
 
+    /// PATTERNS are extended regular expressions
+    #[arg(short = 'E', long = "extended-regexp")]
+    extended_regexp: bool,
+
+    /// PATTERNS are strings
+    #[arg(short = 'F', long = "fixed-strings")]
+    fixed_strings: bool,
+
+    /// PATTERNS are basic regular expressions
+    #[arg(short = 'G', long = "basic-regexp")]
+    basic_regexp: bool,
+
     #[arg(short = 'w', long = "word-regexp")]
     word_regexp: bool,
 
@@ -25,109 +48,280 @@ struct Args {
 
     #[arg(short = 'A', long = "after-context")]
     after_context: Option<usize>,
+
+    /// Print NUM lines of leading context
+    #[arg(short = 'B', long = "before-context", value_name = "NUM")]
+    before_context: Option<usize>,
+
+    /// Print NUM lines of output context
+    #[arg(short = 'C', long = "context", value_name = "NUM")]
+    context: Option<usize>,
+
+    /// Select non-matching lines
+    #[arg(short = 'v', long = "invert-match")]
+    invert_match: bool,
+
+    /// Print line number with output lines
+    #[arg(short = 'n', long = "line-number")]
+    line_number: bool,
+
+    /// Print only a count of selected lines per FILE
+    #[arg(short = 'c', long = "count")]
+    count: bool,
+
+    /// Print only names of FILEs with selected lines
+    #[arg(short = 'l', long = "files-with-matches")]
+    files_with_matches: bool,
+
+    /// Print only names of FILEs with no selected lines
+    #[arg(short = 'L', long = "files-without-match")]
+    files_without_match: bool,
+
+    /// Print file name with output lines
+    #[arg(short = 'H', long = "with-filename", overrides_with = "no_filename")]
+    with_filename: bool,
+
+    /// Suppress the file name prefix on output
+    #[arg(short = 'h', long = "no-filename", overrides_with = "with_filename")]
+    no_filename: bool,
+
+    /// Show only nonempty parts of lines that match
+    #[arg(short = 'o', long = "only-matching")]
+    only_matching: bool,
+
+    /// Suppress all normal output
+    #[arg(short = 'q', long = "quiet", visible_alias = "silent")]
+    quiet: bool,
+
+    /// Search directories recursively, skipping symlinks inside them
+    #[arg(short = 'r', long = "recursive")]
+    recursive: bool,
+
+    /// Search directories recursively, following all symlinks
+    #[arg(short = 'R', long = "dereference-recursive")]
+    dereference_recursive: bool,
+
+    /// Search only files whose base name matches GLOB
+    #[arg(long = "include", value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files whose base name matches GLOB
+    #[arg(long = "exclude", value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Skip directories whose base name matches GLOB
+    #[arg(long = "exclude-dir", value_name = "GLOB")]
+    exclude_dir: Vec<String>,
+
+    /// Assume that binary files are TYPE
+    #[arg(
+        long = "binary-files",
+        value_name = "TYPE",
+        value_enum,
+        default_value_t
+    )]
+    binary_files: BinaryFiles,
+
+    /// Equivalent to --binary-files=text
+    #[arg(short = 'a', long = "text")]
+    text: bool,
+
+    /// Equivalent to --binary-files=without-match
+    #[arg(short = 'I')]
+    skip_binary: bool,
+
+    /// Print help
+    #[arg(long, action = ArgAction::Help)]
+    help: Option<bool>,
 }
 
 /// Implements grep built-in command
 ///
 /// Usage: grep [OPTION...] PATTERNS [FILE...]
-/// TODO: Description
+///
+/// Searches for PATTERNS in each FILE. PATTERNS are newline-separated basic
+/// regular expressions by default; `-E` switches to extended expressions and
+/// `-F` to fixed strings. With `-r` directories are searched recursively.
+///
+/// Exits with 0 if any line was selected, 1 if none was and 2 if some input
+/// could not be read.
 #[derive(Default, Debug)]
 pub struct GrepCommand {}
 
+/// How inputs that look binary, i.e. contain NUL bytes, are treated.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum BinaryFiles {
+    /// Search them, but only report whether they match.
+    #[default]
+    Binary,
+    /// Search them as if they were text.
+    Text,
+    /// Assume that they do not match.
+    WithoutMatch,
+}
+
+/// What grep reports for every input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    Lines,
+    Count,
+    FilesWithMatches,
+    FilesWithoutMatch,
+    Quiet,
+}
+
 pub struct GrepFlags {
+    syntax: PatternSyntax,
     word_regexp: bool,
     ignore_case: bool,
     after_context: usize,
+    before_context: usize,
+    invert_match: bool,
+    output_mode: OutputMode,
+    line_number: bool,
+    with_filename: bool,
+    only_matching: bool,
+    binary_files: BinaryFiles,
+}
+
+impl GrepFlags {
+    fn uses_context(&self) -> bool {
+        self.before_context > 0 || self.after_context > 0
+    }
+
+    /// Modes that only need to know whether an input has a match at all.
+    fn stops_on_first_match(&self) -> bool {
+        matches!(
+            self.output_mode,
+            OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch | OutputMode::Quiet
+        )
+    }
 }
 
 impl GrepCommand {
     const STDIN_WILDCARD: &'static str = "-";
+    const STDIN_LABEL: &'static str = "(standard input)";
     const MATCH_GROUP_DELIM: &'static str = "--";
 
     fn parse_patterns(
         raw_patterns: &str,
         delim: &str,
         flags: &GrepFlags,
-    ) -> Result<RegexSet, regex::Error> {
-        let patterns: Vec<String> = raw_patterns
+    ) -> Result<Matcher, Box<dyn Error + Sync + Send>> {
+        let patterns = raw_patterns
             .split(delim)
             .filter(|s| !s.is_empty())
             .map(|pattern| {
+                let pattern = pattern::translate(pattern, flags.syntax)?;
                 if flags.word_regexp {
-                    format!(r"\b{}\b", regex::escape(pattern))
+                    Ok(format!(r"\b(?:{pattern})\b"))
                 } else {
-                    regex::escape(pattern)
+                    Ok(pattern)
                 }
             })
-            .collect();
+            .collect::<Result<Vec<String>, Box<dyn Error + Sync + Send>>>()?;
 
-        RegexSetBuilder::new(patterns)
-            .case_insensitive(flags.ignore_case)
-            .build()
+        Ok(Matcher::new(patterns, flags.ignore_case)?)
     }
 
+    /// Searches a single input, printing selected lines as soon as they are read.
+    ///
+    /// Returns the number of selected lines.
     fn grep_from_source(
         source: &mut dyn Read,
-        patterns: &RegexSet,
+        file_name: &str,
+        matcher: &Matcher,
         flags: &GrepFlags,
-    ) -> Result<Vec<Vec<String>>, Box<dyn Error + Sync + Send>> {
+        printer: &mut Printer,
+    ) -> Result<usize, Box<dyn Error + Sync + Send>> {
         let mut buffer = BufReader::new(source);
-        let mut matched_line_groups: Vec<Vec<String>> = Vec::new();
+        let is_binary = flags.binary_files != BinaryFiles::Text && buffer.fill_buf()?.contains(&0);
+        if is_binary && flags.binary_files == BinaryFiles::WithoutMatch {
+            printer.print_summary(file_name, 0, is_binary)?;
+            return Ok(0);
+        }
+        // Lines of binary files are never printed, the first match is reported instead.
+        let prints_lines = flags.output_mode == OutputMode::Lines && !is_binary;
 
-        let mut line = String::new();
+        let mut raw_line = Vec::new();
+        let mut line_number = 0;
+        let mut match_count = 0;
         let mut context_count = 0;
-        let mut has_active_group = false;
-        matched_line_groups.push(Vec::new());
-
-        while buffer.read_line(&mut line)? > 0 {
-            if patterns.is_match(&line) {
-                matched_line_groups
-                    .last_mut()
-                    .unwrap()
-                    .push(line.trim().to_string());
+        // Ring buffer of the lines preceding the next selected one.
+        let mut before_lines: VecDeque<(usize, String)> =
+            VecDeque::with_capacity(flags.before_context);
+        let mut last_printed: Option<usize> = None;
+
+        while buffer.read_until(b'\n', &mut raw_line)? > 0 {
+            line_number += 1;
+            let line = String::from_utf8_lossy(&raw_line);
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+
+            if matcher.is_match(line) != flags.invert_match {
+                match_count += 1;
+                if flags.stops_on_first_match()
+                    || (is_binary && flags.output_mode == OutputMode::Lines)
+                {
+                    break;
+                }
+                if !prints_lines {
+                    raw_line.clear();
+                    continue;
+                }
+
+                let group_start = before_lines
+                    .front()
+                    .map_or(line_number, |(number, _)| *number);
+                if last_printed.is_none_or(|last| group_start > last + 1) {
+                    printer.start_group()?;
+                }
+                for (number, context_line) in before_lines.drain(..) {
+                    printer.print_line(file_name, number, &context_line, &[], false)?;
+                }
+
+                let spans = if flags.invert_match || !flags.only_matching {
+                    Vec::new()
+                } else {
+                    matcher.find_spans(line)
+                };
+                printer.print_line(file_name, line_number, line, &spans, true)?;
+                last_printed = Some(line_number);
                 context_count = flags.after_context;
-                has_active_group = context_count > 0;
-            } else if context_count > 0 {
-                matched_line_groups
-                    .last_mut()
-                    .unwrap()
-                    .push(line.trim().to_string());
+            } else if prints_lines && context_count > 0 {
+                printer.print_line(file_name, line_number, line, &[], false)?;
+                last_printed = Some(line_number);
                 context_count -= 1;
-            } else if has_active_group {
-                matched_line_groups.push(Vec::new());
-                has_active_group = false;
+            } else if prints_lines && flags.before_context > 0 {
+                if before_lines.len() == flags.before_context {
+                    before_lines.pop_front();
+                }
+                before_lines.push_back((line_number, line.to_string()));
             }
-            line.clear();
+            raw_line.clear();
         }
 
-        Ok(matched_line_groups)
+        printer.print_summary(file_name, match_count, is_binary)?;
+        Ok(match_count)
     }
 
-    fn format_match_report(match_report: &HashMap<String, Vec<Vec<String>>>) -> String {
-        let multiple_files = match_report.len() > 1;
+    fn display_name(file_name: &str) -> &str {
+        if file_name == Self::STDIN_WILDCARD {
+            Self::STDIN_LABEL
+        } else {
+            file_name
+        }
+    }
 
-        match_report
-            .iter()
-            .flat_map(|(filename, match_groups)| {
-                match_groups
-                    .iter()
-                    .map(|match_group| {
-                        match_group
-                            .iter()
-                            .map(|line| {
-                                if multiple_files {
-                                    format!("{filename}:{line}")
-                                } else {
-                                    line.to_string()
-                                }
-                            })
-                            .collect::<Vec<String>>()
-                    })
-                    .collect::<Vec<Vec<String>>>()
-            })
-            .map(|group| group.join("\n"))
-            .collect::<Vec<String>>()
-            .join(format!("\n{}\n", Self::MATCH_GROUP_DELIM).as_str())
+    /// Searches a file operand, or a file found while recursing.
+    fn search_file(
+        path: &Path,
+        file_name: &str,
+        matcher: &Matcher,
+        flags: &GrepFlags,
+        printer: &mut Printer,
+    ) -> Result<usize, Box<dyn Error + Sync + Send>> {
+        let mut file = File::open(path)?;
+        Self::grep_from_source(&mut file, file_name, matcher, flags, printer)
     }
 }
 
@@ -135,42 +329,379 @@ impl BuiltinCommand for GrepCommand {
     fn exec(
         &self,
         args: Vec<String>,
+        shell: &mut Shell,
         stdin: &mut dyn Read,
-        _stderr: &mut dyn Write,
+        stderr: &mut dyn Write,
         stdout: &mut dyn Write,
-    ) -> Result<(), Box<dyn Error + Sync + Send>> {
-        let args = Args::try_parse_from(args.into_iter())?;
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let recursive = args.recursive || args.dereference_recursive;
 
+        let mut files = args.files;
+        // Without operands a recursive search looks into the working directory.
+        let implicit_operand = files.is_empty() && recursive;
+        if files.is_empty() {
+            files.push(String::from(if recursive {
+                "."
+            } else {
+                Self::STDIN_WILDCARD
+            }));
+        }
+
+        let syntax = if args.extended_regexp {
+            PatternSyntax::Extended
+        } else if args.fixed_strings {
+            PatternSyntax::Fixed
+        } else {
+            PatternSyntax::Basic
+        };
+        let output_mode = if args.quiet {
+            OutputMode::Quiet
+        } else if args.files_with_matches {
+            OutputMode::FilesWithMatches
+        } else if args.files_without_match {
+            OutputMode::FilesWithoutMatch
+        } else if args.count {
+            OutputMode::Count
+        } else {
+            OutputMode::Lines
+        };
+        let binary_files = if args.text {
+            BinaryFiles::Text
+        } else if args.skip_binary {
+            BinaryFiles::WithoutMatch
+        } else {
+            args.binary_files
+        };
         let grep_flags = GrepFlags {
+            syntax,
             word_regexp: args.word_regexp,
             ignore_case: args.ignore_case,
-            after_context: args.after_context.unwrap_or(0),
+            after_context: args.after_context.or(args.context).unwrap_or(0),
+            before_context: args.before_context.or(args.context).unwrap_or(0),
+            invert_match: args.invert_match,
+            output_mode,
+            line_number: args.line_number,
+            with_filename: (args.with_filename || files.len() > 1 || recursive)
+                && !args.no_filename,
+            only_matching: args.only_matching,
+            binary_files,
         };
-        let patterns = Self::parse_patterns(&args.patterns, "\n", &grep_flags)?;
+        let matcher = Self::parse_patterns(&args.patterns, "\n", &grep_flags)?;
+        let filter = FileFilter::new(&args.include, &args.exclude, &args.exclude_dir)?;
 
-        let mut files = args.files;
-        if files.is_empty() {
-            files.push(String::from(Self::STDIN_WILDCARD));
-        }
-
-        let mut match_report: HashMap<String, Vec<Vec<String>>> = HashMap::default();
+        let mut match_count = 0;
+        let mut has_errors = false;
+        let mut printer = Printer::new(stdout, &grep_flags);
         for file_name in files {
-            let matched_lines = if file_name == Self::STDIN_WILDCARD {
-                Self::grep_from_source(stdin, &patterns, &grep_flags)?
+            if file_name == Self::STDIN_WILDCARD {
+                match_count +=
+                    Self::grep_from_source(stdin, &file_name, &matcher, &grep_flags, &mut printer)?;
+            } else if recursive {
+                let walker = Walker::new(
+                    PathBuf::from(&file_name),
+                    shell.current_dir(),
+                    args.dereference_recursive,
+                    &filter,
+                );
+                for entry in walker {
+                    let result = match entry {
+                        Ok(path) => {
+                            let name = path.to_string_lossy();
+                            let name = match name.strip_prefix("./") {
+                                Some(stripped) if implicit_operand => stripped,
+                                _ => &name,
+                            };
+                            let path = shell.resolve(&path);
+                            match Self::search_file(
+                                &path,
+                                name,
+                                &matcher,
+                                &grep_flags,
+                                &mut printer,
+                            ) {
+                                Err(err) if is_broken_pipe(err.as_ref()) => return Err(err),
+                                result => result.map_err(|err| format!("{name}: {err}")),
+                            }
+                        }
+                        Err((path, err)) => Err(format!("{}: {err}", path.display())),
+                    };
+                    match result {
+                        Ok(count) => match_count += count,
+                        Err(message) => {
+                            writeln!(stderr, "grep: {message}")?;
+                            has_errors = true;
+                        }
+                    }
+                    if output_mode == OutputMode::Quiet && match_count > 0 {
+                        return Ok(ExitStatus::new(Some(0)));
+                    }
+                }
             } else {
-                let mut file = File::open(&file_name)?;
-                Self::grep_from_source(&mut file, &patterns, &grep_flags)?
-            };
-            match_report.insert(file_name, matched_lines);
+                let path = shell.resolve(&file_name);
+                if path.is_dir() {
+                    writeln!(stderr, "grep: {file_name}: Is a directory")?;
+                    has_errors = true;
+                } else if filter.accepts_file(&path) {
+                    match Self::search_file(&path, &file_name, &matcher, &grep_flags, &mut printer)
+                    {
+                        Ok(count) => match_count += count,
+                        Err(err) if is_broken_pipe(err.as_ref()) => return Err(err),
+                        Err(err) => {
+                            writeln!(stderr, "grep: {file_name}: {err}")?;
+                            has_errors = true;
+                        }
+                    }
+                }
+            }
+
+            if output_mode == OutputMode::Quiet && match_count > 0 {
+                return Ok(ExitStatus::new(Some(0)));
+            }
         }
 
-        let match_report_str = Self::format_match_report(&match_report);
-        stdout.write_all(match_report_str.as_bytes())?;
-        writeln!(stdout)?;
-        Ok(())
+        let code = if has_errors {
+            2
+        } else if match_count > 0 {
+            0
+        } else {
+            1
+        };
+        Ok(ExitStatus::new(Some(code)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
     }
 
     fn tag(&self) -> &'static str {
         "grep"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{BufRead, BufReader, Write},
+        thread,
+    };
+
+    use super::*;
+    use crate::{backend::Backend, frontend::Frontend};
+
+    fn exec_grep(
+        argv: Vec<String>,
+        stdin: &mut dyn std::io::Read,
+        stderr: &mut dyn Write,
+        stdout: &mut dyn Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let mut shell = Shell {
+            frontend: &mut Frontend::new(),
+            backend: &Backend::new(),
+        };
+        GrepCommand::default().exec(argv, &mut shell, stdin, stderr, stdout)
+    }
+
+    fn grep_with_status(args: &[&str], input: &str) -> (String, Option<i32>) {
+        let mut argv = vec![String::from("grep")];
+        argv.extend(args.iter().map(|arg| arg.to_string()));
+
+        let mut stdout = Vec::new();
+        let status = exec_grep(argv, &mut input.as_bytes(), &mut Vec::new(), &mut stdout).unwrap();
+        (String::from_utf8(stdout).unwrap(), status.code())
+    }
+
+    fn grep(args: &[&str], input: &str) -> String {
+        grep_with_status(args, input).0
+    }
+
+    #[test]
+    fn test_pattern_syntax() {
+        let input = "a.c\nabc\na+c\naac\n";
+
+        assert_eq!(grep(&["a.c"], input), "a.c\nabc\na+c\naac\n");
+        assert_eq!(grep(&["-F", "a.c"], input), "a.c\n");
+        assert_eq!(grep(&["a+c"], input), "a+c\n");
+        assert_eq!(grep(&["-E", "a+c"], input), "aac\n");
+        assert_eq!(grep(&[r"a\(b\|+\)c"], input), "abc\na+c\n");
+        assert_eq!(grep(&["-E", "a(b|a)c"], input), "abc\naac\n");
+    }
+
+    #[test]
+    fn test_conflicting_syntax_flags() {
+        let argv = ["grep", "-E", "-F", "a"].map(String::from).to_vec();
+        let result = exec_grep(argv, &mut "".as_bytes(), &mut Vec::new(), &mut Vec::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_output_modes() {
+        let input = "foo bar\nbaz\nfoo foo\n";
+
+        assert_eq!(grep(&["-n", "foo"], input), "1:foo bar\n3:foo foo\n");
+        assert_eq!(grep(&["-n", "-A", "1", "bar"], input), "1:foo bar\n2-baz\n");
+        assert_eq!(grep(&["-c", "foo"], input), "2\n");
+        assert_eq!(grep(&["-o", "fo*"], input), "foo\nfoo\nfoo\n");
+        assert_eq!(grep(&["-on", "ba."], input), "1:bar\n2:baz\n");
+        assert_eq!(grep(&["-H", "baz"], input), "(standard input):baz\n");
+        assert_eq!(grep(&["-Hh", "baz"], input), "baz\n");
+        assert_eq!(grep(&["-l", "baz"], input), "(standard input)\n");
+        assert_eq!(grep(&["-L", "baz"], input), "");
+        assert_eq!(grep(&["-L", "qux"], input), "(standard input)\n");
+    }
+
+    #[test]
+    fn test_context() {
+        let input = "a\nb\nmatch1\nc\nd\ne\nmatch2\nf\nmatch3\ng\n";
+
+        assert_eq!(
+            grep(&["-B", "1", "match"], input),
+            "b\nmatch1\n--\ne\nmatch2\nf\nmatch3\n"
+        );
+        assert_eq!(
+            grep(&["-n", "-C", "1", "match"], input),
+            "2-b\n3:match1\n4-c\n--\n6-e\n7:match2\n8-f\n9:match3\n10-g\n"
+        );
+        assert_eq!(
+            grep(&["-C", "2", "match"], input),
+            "a\nb\nmatch1\nc\nd\ne\nmatch2\nf\nmatch3\ng\n"
+        );
+        assert_eq!(grep(&["-B", "5", "-A", "0", "b"], input), "a\nb\n");
+    }
+
+    #[test]
+    fn test_invert_match() {
+        let input = "a\nb\nmatch1\nc\nmatch2\n";
+
+        assert_eq!(grep(&["-v", "match"], input), "a\nb\nc\n");
+        assert_eq!(grep(&["-vc", "match"], input), "3\n");
+        assert_eq!(
+            grep(&["-vn", "-A", "1", "^[abc]$"], input),
+            "3:match1\n4-c\n5:match2\n"
+        );
+    }
+
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(
+            grep_with_status(&["-q", "b"], "a\nb\n"),
+            (String::new(), Some(0))
+        );
+        assert_eq!(
+            grep_with_status(&["-q", "c"], "a\nb\n"),
+            (String::new(), Some(1))
+        );
+        assert_eq!(grep_with_status(&["c"], "a\nb\n"), (String::new(), Some(1)));
+        assert_eq!(
+            grep_with_status(&["-c", "c"], "a\nb\n"),
+            (String::from("0\n"), Some(1))
+        );
+    }
+
+    #[test]
+    fn test_recursive() {
+        let root = env::temp_dir().join(format!("shell-grep-recursive-{}", std::process::id()));
+        for dir in ["sub", "skip"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let files = [
+            ("a.txt", "foo\n"),
+            ("b.log", "foo\n"),
+            ("bin.dat", "foo\0\n"),
+            ("skip/d.txt", "foo\n"),
+            ("sub/c.txt", "bar\nfoo\n"),
+        ];
+        for (name, content) in files {
+            fs::write(root.join(name), content).unwrap();
+        }
+        let root_str = root.to_str().unwrap();
+
+        assert_eq!(
+            grep(&["-r", "foo", root_str], ""),
+            [
+                format!("{root_str}/a.txt:foo\n"),
+                format!("{root_str}/b.log:foo\n"),
+                format!("Binary file {root_str}/bin.dat matches\n"),
+                format!("{root_str}/skip/d.txt:foo\n"),
+                format!("{root_str}/sub/c.txt:foo\n"),
+            ]
+            .concat()
+        );
+        assert_eq!(
+            grep(
+                &[
+                    "-rn",
+                    "--include=*.txt",
+                    "--exclude-dir=skip",
+                    "foo",
+                    root_str
+                ],
+                ""
+            ),
+            format!("{root_str}/a.txt:1:foo\n{root_str}/sub/c.txt:2:foo\n")
+        );
+        assert_eq!(
+            grep(&["-rcI", "--exclude=*.txt", "foo", root_str], ""),
+            format!("{root_str}/b.log:1\n{root_str}/bin.dat:0\n")
+        );
+
+        let argv = ["grep", "foo", root_str].map(String::from).to_vec();
+        let mut stderr = Vec::new();
+        let status = exec_grep(argv, &mut "".as_bytes(), &mut stderr, &mut Vec::new()).unwrap();
+        assert_eq!(status.code(), Some(2));
+        assert_eq!(
+            String::from_utf8(stderr).unwrap(),
+            format!("grep: {root_str}: Is a directory\n")
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_keeps_whitespace() {
+        assert_eq!(grep(&["b"], "  a b \n\tb\n"), "  a b \n\tb\n");
+    }
+
+    #[test]
+    fn test_keeps_operand_order() {
+        let dir = env::temp_dir().join(format!("shell-grep-order-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let names = ["z", "m", "a"];
+        for name in names {
+            fs::write(dir.join(name), format!("{name}\n")).unwrap();
+        }
+
+        let paths: Vec<String> = names
+            .iter()
+            .map(|name| dir.join(name).to_string_lossy().into_owned())
+            .collect();
+        let mut args = vec!["-h", "."];
+        args.extend(paths.iter().map(String::as_str));
+        assert_eq!(grep(&args, ""), "z\nm\na\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_streams_matches() {
+        let (stdin_reader, mut stdin_writer) = os_pipe::pipe().unwrap();
+        let (stdout_reader, mut stdout_writer) = os_pipe::pipe().unwrap();
+
+        let grep = thread::spawn(move || {
+            let mut stdin_reader = stdin_reader;
+            let argv = ["grep", "match"].map(String::from).to_vec();
+            exec_grep(argv, &mut stdin_reader, &mut Vec::new(), &mut stdout_writer).unwrap()
+        });
+
+        // The match is printed while the input is still open.
+        let mut stdout = BufReader::new(stdout_reader);
+        let mut line = String::new();
+        writeln!(stdin_writer, "skip\nmatch 1").unwrap();
+        stdout.read_line(&mut line).unwrap();
+        assert_eq!(line, "match 1\n");
+
+        drop(stdin_writer);
+        assert_eq!(grep.join().unwrap().code(), Some(0));
+    }
+}
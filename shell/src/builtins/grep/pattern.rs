@@ -0,0 +1,288 @@
+use std::{error::Error, iter::Peekable, ops::Range, str::Chars};
+
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+
+/// Pattern dialect selected by the `-G`, `-E` and `-F` flags.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PatternSyntax {
+    /// POSIX basic regular expressions (the default).
+    #[default]
+    Basic,
+    /// POSIX extended regular expressions.
+    Extended,
+    /// Patterns are matched as plain strings.
+    Fixed,
+}
+
+/// Compiled grep patterns.
+///
+/// The `RegexSet` answers whether a line matches any pattern at all, while the
+/// individual expressions are only consulted to locate the matched parts.
+pub struct Matcher {
+    set: RegexSet,
+    regexes: Vec<Regex>,
+}
+
+impl Matcher {
+    pub fn new(patterns: Vec<String>, ignore_case: bool) -> Result<Self, regex::Error> {
+        let set = RegexSetBuilder::new(&patterns)
+            .case_insensitive(ignore_case)
+            .build()?;
+        let regexes = patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .build()
+            })
+            .collect::<Result<Vec<Regex>, regex::Error>>()?;
+
+        Ok(Self { set, regexes })
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.set.is_match(line)
+    }
+
+    /// Returns non-empty, non-overlapping matches of any pattern in `line`,
+    /// preferring the leftmost and then the longest one.
+    pub fn find_spans(&self, line: &str) -> Vec<Range<usize>> {
+        let mut candidates: Vec<Range<usize>> = self
+            .set
+            .matches(line)
+            .into_iter()
+            .flat_map(|idx| self.regexes[idx].find_iter(line))
+            .filter(|m| !m.is_empty())
+            .map(|m| m.range())
+            .collect();
+        candidates.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+        let mut spans: Vec<Range<usize>> = Vec::new();
+        for span in candidates {
+            if spans.last().is_none_or(|last| last.end <= span.start) {
+                spans.push(span);
+            }
+        }
+        spans
+    }
+}
+
+/// Translates a single grep pattern into the syntax understood by the `regex` crate.
+pub fn translate(
+    pattern: &str,
+    syntax: PatternSyntax,
+) -> Result<String, Box<dyn Error + Sync + Send>> {
+    match syntax {
+        PatternSyntax::Fixed => Ok(regex::escape(pattern)),
+        PatternSyntax::Extended => translate_extended(pattern),
+        PatternSyntax::Basic => translate_basic(pattern),
+    }
+}
+
+/// Extended expressions mostly share syntax with `regex`, so only bracket
+/// expressions need rewriting: POSIX treats backslashes inside them literally.
+fn translate_extended(pattern: &str) -> Result<String, Box<dyn Error + Sync + Send>> {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(digit @ '1'..='9') => return Err(backreference_error(digit)),
+                Some(escaped) => {
+                    out.push('\\');
+                    out.push(escaped);
+                }
+                None => return Err("trailing backslash (\\)".into()),
+            },
+            '[' => translate_bracket(&mut chars, &mut out)?,
+            _ => out.push(ch),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Basic expressions swap the meaning of escaped and unescaped `( ) { } | + ?`,
+/// and treat `*`, `^` and `$` literally where they cannot act as operators.
+fn translate_basic(pattern: &str) -> Result<String, Box<dyn Error + Sync + Send>> {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    // True when the next token starts a (sub)expression, i.e. `*` is literal there.
+    let mut at_expression_start = true;
+
+    while let Some(ch) = chars.next() {
+        let starts_expression = match ch {
+            '\\' => match chars.next() {
+                Some(op @ ('(' | '|')) => {
+                    out.push(op);
+                    true
+                }
+                Some(op @ (')' | '{' | '}' | '+' | '?')) => {
+                    out.push(op);
+                    false
+                }
+                Some(digit @ '1'..='9') => return Err(backreference_error(digit)),
+                Some(class @ ('w' | 'W' | 's' | 'S' | 'b' | 'B' | '<' | '>')) => {
+                    out.push('\\');
+                    out.push(class);
+                    false
+                }
+                Some(literal) => {
+                    push_literal(&mut out, literal);
+                    false
+                }
+                None => return Err("trailing backslash (\\)".into()),
+            },
+            '*' if at_expression_start => {
+                push_literal(&mut out, ch);
+                false
+            }
+            '^' if at_expression_start => {
+                out.push(ch);
+                true
+            }
+            '$' if ends_expression(&chars) => {
+                out.push(ch);
+                false
+            }
+            '(' | ')' | '{' | '}' | '|' | '+' | '?' | '^' | '$' => {
+                push_literal(&mut out, ch);
+                false
+            }
+            '[' => {
+                translate_bracket(&mut chars, &mut out)?;
+                false
+            }
+            _ => {
+                out.push(ch);
+                false
+            }
+        };
+        at_expression_start = starts_expression;
+    }
+
+    Ok(out)
+}
+
+/// Checks whether a `$` at the current position is followed by the end of
+/// the pattern, `\)` or `\|`.
+fn ends_expression(chars: &Peekable<Chars>) -> bool {
+    let mut rest = chars.clone();
+    match rest.next() {
+        None => true,
+        Some('\\') => matches!(rest.next(), Some(')' | '|')),
+        Some(_) => false,
+    }
+}
+
+/// Copies a bracket expression whose opening `[` was already consumed.
+fn translate_bracket(
+    chars: &mut Peekable<Chars>,
+    out: &mut String,
+) -> Result<(), Box<dyn Error + Sync + Send>> {
+    out.push('[');
+    if chars.next_if_eq(&'^').is_some() {
+        out.push('^');
+    }
+    // A leading `]` is a member of the set rather than its end.
+    if chars.next_if_eq(&']').is_some() {
+        out.push_str(r"\]");
+    }
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            ']' => {
+                out.push(']');
+                return Ok(());
+            }
+            '[' if matches!(chars.peek(), Some(':' | '=' | '.')) => {
+                let kind = chars.next().unwrap();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == kind && chars.peek() == Some(&']') => {
+                            chars.next();
+                            break;
+                        }
+                        Some(c) => name.push(c),
+                        None => return Err("unmatched [, [^, [:, [., or [=".into()),
+                    }
+                }
+                if kind == ':' {
+                    out.push_str(&format!("[:{name}:]"));
+                } else {
+                    name.chars().for_each(|c| push_literal(out, c));
+                }
+            }
+            '\\' | '[' | '&' | '~' => push_literal(out, ch),
+            _ => out.push(ch),
+        }
+    }
+
+    Err("unmatched [, [^, [:, [., or [=".into())
+}
+
+fn push_literal(out: &mut String, ch: char) {
+    out.push_str(&regex::escape(ch.encode_utf8(&mut [0; 4])));
+}
+
+fn backreference_error(digit: char) -> Box<dyn Error + Sync + Send> {
+    format!("back-references are not supported (\\{digit})").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_basic() {
+        let cases = [
+            (r"a\(b\|c\)", r"a(b|c)"),
+            (r"a(b|c)", r"a\(b\|c\)"),
+            (r"ab\{2,3\}", r"ab{2,3}"),
+            (r"a+b?", r"a\+b\?"),
+            (r"a\+", r"a+"),
+            (r"*a*", r"\*a*"),
+            (r"^*a", r"^\*a"),
+            (r"\(*a\)", r"(\*a)"),
+            (r"a^b$c$", r"a\^b\$c$"),
+            (r"\(a$\)", r"(a$)"),
+            (r"\.\*", r"\.\*"),
+            (r"[]a\]", r"[\]a\\]"),
+            (r"[[:digit:]x]", r"[[:digit:]x]"),
+        ];
+
+        for (pattern, expected) in cases {
+            assert_eq!(
+                translate(pattern, PatternSyntax::Basic).unwrap(),
+                expected,
+                "{pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_translate_extended() {
+        assert_eq!(
+            translate(r"(a|b)+[\d]", PatternSyntax::Extended).unwrap(),
+            r"(a|b)+[\\d]"
+        );
+        assert_eq!(translate(r"a.b*", PatternSyntax::Fixed).unwrap(), r"a\.b\*");
+    }
+
+    #[test]
+    fn test_find_spans() {
+        let matcher = Matcher::new(vec!["ab".into(), "abc".into(), "c+".into()], false).unwrap();
+        assert_eq!(matcher.find_spans("xabcab ccc"), vec![1..4, 4..6, 7..10]);
+        assert!(matcher.find_spans("xyz").is_empty());
+    }
+
+    #[test]
+    fn test_translate_errors() {
+        assert!(translate(r"\(a\)\1", PatternSyntax::Basic).is_err());
+        assert!(translate(r"(a)\1", PatternSyntax::Extended).is_err());
+        assert!(translate(r"[abc", PatternSyntax::Basic).is_err());
+        assert!(translate(r"abc\", PatternSyntax::Basic).is_err());
+    }
+}
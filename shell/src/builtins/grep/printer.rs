@@ -0,0 +1,86 @@
+use std::{
+    io::{self, Write},
+    ops::Range,
+};
+
+use super::{GrepCommand, GrepFlags, OutputMode};
+
+/// Writes grep results to the output as soon as they are found.
+pub struct Printer<'a> {
+    out: &'a mut dyn Write,
+    flags: &'a GrepFlags,
+    /// Whether a line group was printed before, possibly for another input.
+    has_output: bool,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(out: &'a mut dyn Write, flags: &'a GrepFlags) -> Self {
+        Self {
+            out,
+            flags,
+            has_output: false,
+        }
+    }
+
+    /// Separates a new group of lines from the previous one when context is shown.
+    pub fn start_group(&mut self) -> io::Result<()> {
+        if self.has_output && self.flags.uses_context() {
+            writeln!(self.out, "{}", GrepCommand::MATCH_GROUP_DELIM)?;
+        }
+        Ok(())
+    }
+
+    /// Prints a selected line (`is_match`) or a context line without its terminator.
+    pub fn print_line(
+        &mut self,
+        file_name: &str,
+        number: usize,
+        line: &str,
+        spans: &[Range<usize>],
+        is_match: bool,
+    ) -> io::Result<()> {
+        self.has_output = true;
+
+        let sep = if is_match { ':' } else { '-' };
+        let mut prefix = String::new();
+        if self.flags.with_filename {
+            prefix.push_str(&format!("{}{sep}", GrepCommand::display_name(file_name)));
+        }
+        if self.flags.line_number {
+            prefix.push_str(&format!("{number}{sep}"));
+        }
+
+        if self.flags.only_matching {
+            for span in spans {
+                writeln!(self.out, "{prefix}{}", &line[span.clone()])?;
+            }
+        } else {
+            writeln!(self.out, "{prefix}{line}")?;
+        }
+        Ok(())
+    }
+
+    /// Prints what is reported once an input has been searched.
+    pub fn print_summary(
+        &mut self,
+        file_name: &str,
+        match_count: usize,
+        is_binary: bool,
+    ) -> io::Result<()> {
+        let display_name = GrepCommand::display_name(file_name);
+        match self.flags.output_mode {
+            OutputMode::FilesWithMatches if match_count > 0 => writeln!(self.out, "{display_name}"),
+            OutputMode::FilesWithoutMatch if match_count == 0 => {
+                writeln!(self.out, "{display_name}")
+            }
+            OutputMode::Count if self.flags.with_filename => {
+                writeln!(self.out, "{display_name}:{match_count}")
+            }
+            OutputMode::Count => writeln!(self.out, "{match_count}"),
+            OutputMode::Lines if is_binary && match_count > 0 => {
+                writeln!(self.out, "Binary file {display_name} matches")
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use glob::Pattern;
+
+/// Glob filters applied to the files visited by a recursive search.
+#[derive(Default)]
+pub struct FileFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    exclude_dir: Vec<Pattern>,
+}
+
+impl FileFilter {
+    pub fn new(
+        include: &[String],
+        exclude: &[String],
+        exclude_dir: &[String],
+    ) -> Result<Self, glob::PatternError> {
+        let compile = |globs: &[String]| {
+            globs
+                .iter()
+                .map(|glob| Pattern::new(glob))
+                .collect::<Result<Vec<Pattern>, glob::PatternError>>()
+        };
+
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+            exclude_dir: compile(exclude_dir)?,
+        })
+    }
+
+    /// Checks a file against `--include` and `--exclude` using its base name.
+    pub fn accepts_file(&self, path: &Path) -> bool {
+        let name = base_name(path);
+        let included =
+            self.include.is_empty() || self.include.iter().any(|glob| glob.matches(&name));
+        included && !self.exclude.iter().any(|glob| glob.matches(&name))
+    }
+
+    /// Checks a directory found during recursion against `--exclude-dir`.
+    pub fn accepts_dir(&self, path: &Path) -> bool {
+        let name = base_name(path);
+        !self.exclude_dir.iter().any(|glob| glob.matches(&name))
+    }
+}
+
+fn base_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Depth-first iterator over the files below a directory.
+///
+/// Entries of every directory are visited in byte order of their names, so the
+/// output is deterministic, and directories are read lazily as the iteration
+/// reaches them. Symbolic links found inside the tree are skipped unless
+/// `follow_links` is set, in which case directories already visited are not
+/// entered again.
+///
+/// Relative paths are resolved against `base`, but yielded unchanged.
+pub struct Walker<'a> {
+    root: Option<PathBuf>,
+    base: PathBuf,
+    stack: Vec<PathBuf>,
+    visited: HashSet<PathBuf>,
+    follow_links: bool,
+    filter: &'a FileFilter,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(root: PathBuf, base: PathBuf, follow_links: bool, filter: &'a FileFilter) -> Self {
+        Self {
+            root: Some(root),
+            base,
+            stack: Vec::new(),
+            visited: HashSet::new(),
+            follow_links,
+            filter,
+        }
+    }
+
+    fn enter_dir(&mut self, dir: &Path) -> io::Result<()> {
+        let resolved = self.base.join(dir);
+        if self.follow_links && !self.visited.insert(fs::canonicalize(&resolved)?) {
+            return Ok(());
+        }
+
+        let mut entries = fs::read_dir(resolved)?
+            .map(|entry| entry.map(|entry| dir.join(entry.file_name())))
+            .collect::<io::Result<Vec<PathBuf>>>()?;
+        // Reverse order, so that popping from the stack yields sorted entries.
+        entries.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+        self.stack.extend(entries);
+        Ok(())
+    }
+}
+
+impl Iterator for Walker<'_> {
+    type Item = Result<PathBuf, (PathBuf, io::Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, is_root) = match self.root.take() {
+                Some(root) => (root, true),
+                None => (self.stack.pop()?, false),
+            };
+
+            // Command line operands are always followed, like `grep -r` does.
+            let resolved = self.base.join(&path);
+            let metadata = if self.follow_links || is_root {
+                fs::metadata(resolved)
+            } else {
+                fs::symlink_metadata(resolved)
+            };
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(err) => return Some(Err((path, err))),
+            };
+
+            if metadata.is_dir() {
+                if !is_root && !self.filter.accepts_dir(&path) {
+                    continue;
+                }
+                if let Err(err) = self.enter_dir(&path) {
+                    return Some(Err((path, err)));
+                }
+            } else if metadata.is_file() && self.filter.accepts_file(&path) {
+                return Some(Ok(path));
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    backend::{ExitStatus, JobState, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::{error::Error, io::Write};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// List process ids in addition to the normal information.
+    #[arg(short = 'l')]
+    long: bool,
+
+    /// List only the process ids of the jobs.
+    #[arg(short = 'p', conflicts_with = "long")]
+    pids: bool,
+
+    jobspecs: Vec<String>,
+}
+
+/// Implements the jobs built-in command.
+///
+/// JobsCommand prints the jobs of the shell with their state, and forgets
+/// the jobs that are reported as done.
+#[derive(Default, Debug)]
+pub struct JobsCommand;
+
+impl BuiltinCommand for JobsCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn Write,
+        stdout: &mut dyn Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let mut jobs = shell.backend.jobs();
+        jobs.update();
+
+        let ids = if args.jobspecs.is_empty() {
+            jobs.ids()
+        } else {
+            args.jobspecs
+                .iter()
+                .map(|spec| jobs.find(spec))
+                .collect::<Result<Vec<usize>, String>>()
+                .map_err(|err| format!("jobs: {err}"))?
+        };
+
+        for id in ids {
+            let job = jobs.get(id).unwrap();
+            if args.pids {
+                match job.pgid {
+                    Some(pgid) => writeln!(stdout, "{pgid}")?,
+                    None => {
+                        for pid in job.pids() {
+                            writeln!(stdout, "{pid}")?;
+                        }
+                    }
+                }
+                continue;
+            }
+            writeln!(stdout, "{}", jobs.format(job, args.long))?;
+            if matches!(job.state, JobState::Done(_)) {
+                jobs.remove(id);
+            }
+        }
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "jobs"
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::Pid,
+};
+use std::{error::Error, io::Write, str::FromStr};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Name or number of the signal to send.
+    #[arg(short = 's', value_parser = parse_signal, conflicts_with = "number")]
+    signal: Option<Signal>,
+
+    /// Number of the signal to send.
+    #[arg(short = 'n', value_parser = parse_signal)]
+    number: Option<Signal>,
+
+    /// List signal names, or the names of the given signal numbers or exit statuses.
+    #[arg(short = 'l', conflicts_with_all = ["signal", "number"])]
+    list: bool,
+
+    /// Process ids or job specs.
+    #[arg(allow_negative_numbers = true)]
+    targets: Vec<String>,
+}
+
+fn parse_signal(value: &str) -> Result<Signal, String> {
+    if let Ok(number) = value.parse::<i32>() {
+        return Signal::try_from(number)
+            .map_err(|_| format!("{value}: invalid signal specification"));
+    }
+    let name = value.to_uppercase();
+    let name = if name.starts_with("SIG") {
+        name
+    } else {
+        format!("SIG{name}")
+    };
+    Signal::from_str(&name).map_err(|_| format!("{value}: invalid signal specification"))
+}
+
+/// Rewrites the `-SIGNAL` form, e.g. `-9` or `-TERM`, into `-s SIGNAL`.
+fn normalize_args(mut args: Vec<String>) -> Vec<String> {
+    let Some(first) = args.get(1) else {
+        return args;
+    };
+    if let Some(name) = first.strip_prefix('-') {
+        if !name.is_empty() && !matches!(name, "s" | "n" | "l" | "-") && parse_signal(name).is_ok()
+        {
+            let name = name.to_string();
+            args.splice(1..2, [String::from("-s"), name]);
+        }
+    }
+    args
+}
+
+/// Implements the kill built-in command.
+///
+/// KillCommand sends a signal, SIGTERM by default, to processes and jobs.
+#[derive(Default, Debug)]
+pub struct KillCommand;
+
+impl BuiltinCommand for KillCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        stderr: &mut dyn Write,
+        stdout: &mut dyn Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(normalize_args(args))?;
+
+        if args.list {
+            if args.targets.is_empty() {
+                let names: Vec<&str> = Signal::iterator()
+                    .map(|signal| &signal.as_str()[3..])
+                    .collect();
+                writeln!(stdout, "{}", names.join(" "))?;
+            }
+            for target in &args.targets {
+                // Exit statuses of killed commands are 128 plus the signal number.
+                let number = target
+                    .parse::<i32>()
+                    .map(|n| if n > 128 { n - 128 } else { n });
+                let signal = number.ok().and_then(|number| Signal::try_from(number).ok());
+                let signal = signal
+                    .ok_or_else(|| format!("kill: {target}: invalid signal specification"))?;
+                writeln!(stdout, "{}", &signal.as_str()[3..])?;
+            }
+            return Ok(ExitStatus::new(Some(0)));
+        }
+
+        if args.targets.is_empty() {
+            return Err(
+                "kill: usage: kill [-s sigspec | -n signum | -sigspec] pid | jobspec ...".into(),
+            );
+        }
+
+        let signal = args.signal.or(args.number).unwrap_or(Signal::SIGTERM);
+        let mut code = 0;
+        for target in &args.targets {
+            let result = if target.starts_with('%') {
+                let jobs = shell.backend.jobs();
+                jobs.find(target)
+                    .and_then(|id| jobs.get(id).unwrap().signal(signal))
+            } else {
+                match target.parse::<i32>() {
+                    Ok(pid) => {
+                        signal::kill(Pid::from_raw(pid), signal).map_err(|err| err.to_string())
+                    }
+                    Err(_) => Err(String::from("arguments must be process or job IDs")),
+                }
+            };
+            if let Err(err) = result {
+                writeln!(stderr, "kill: {target}: {err}")?;
+                code = 1;
+            }
+        }
+        Ok(ExitStatus::new(Some(code)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "kill"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signal_forms() {
+        assert_eq!(parse_signal("9"), Ok(Signal::SIGKILL));
+        assert_eq!(parse_signal("term"), Ok(Signal::SIGTERM));
+        assert_eq!(parse_signal("SIGHUP"), Ok(Signal::SIGHUP));
+        assert!(parse_signal("NOPE").is_err());
+
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            normalize_args(args(&["kill", "-9", "1"])),
+            args(&["kill", "-s", "9", "1"])
+        );
+        assert_eq!(
+            normalize_args(args(&["kill", "-l", "137"])),
+            args(&["kill", "-l", "137"])
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    frontend::arith,
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Arithmetic expressions, evaluated from left to right.
+    #[arg(required = true, allow_hyphen_values = true)]
+    exprs: Vec<String>,
+}
+
+/// Implements the let built-in command.
+///
+/// LetCommand evaluates arithmetic expressions the same way as `$(( ))`. It
+/// succeeds if the last expression is not 0.
+#[derive(Default, Debug)]
+pub struct LetCommand;
+
+impl BuiltinCommand for LetCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let mut value = 0;
+        for expr in args.exprs {
+            let expr = arith::parse(&expr).map_err(|err| format!("let: {err}"))?;
+            value = arith::eval(&expr, shell.frontend.env_mut())
+                .map_err(|err| format!("let: {err}"))?;
+        }
+        Ok(ExitStatus::new(Some(if value == 0 { 1 } else { 0 })))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "let"
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    frontend::Environment,
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Variables to declare, written as `name` or `name=value`.
+    names: Vec<String>,
+}
+
+/// Implements the local built-in command.
+///
+/// LocalCommand declares variables that are visible only in the running
+/// function and the functions it calls. Their previous values come back when
+/// the function returns.
+#[derive(Default, Debug)]
+pub struct LocalCommand;
+
+impl BuiltinCommand for LocalCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let env = shell.frontend.env_mut();
+        if !env.in_function() {
+            return Err("local: can only be used in a function".into());
+        }
+
+        let mut code = 0;
+        for arg in args.names {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if !Environment::is_valid_name(name) {
+                writeln!(stderr, "local: `{arg}': not a valid identifier")?;
+                code = 1;
+                continue;
+            }
+            env.make_local(name);
+            if let Some(value) = value {
+                env.set(name, value);
+            }
+        }
+        Ok(ExitStatus::new(Some(code)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "local"
+    }
+}
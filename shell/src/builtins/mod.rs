@@ -1,6 +1,48 @@
+pub mod alias;
+pub mod bg;
+pub mod r#break;
 pub mod cat;
+pub mod cd;
+pub mod r#continue;
 pub mod echo;
+pub mod env;
 pub mod exit;
+pub mod export;
+pub mod fg;
 pub mod grep;
+pub mod jobs;
+pub mod kill;
+pub mod r#let;
+pub mod local;
 pub mod pwd;
+pub mod r#return;
+pub mod set;
+pub mod shift;
+pub mod unalias;
+pub mod unset;
+pub mod wait;
 pub mod wc;
+
+/// Whether an error of a builtin comes from writing to a pipe that has no
+/// reader anymore.
+pub fn is_broken_pipe(err: &(dyn std::error::Error + 'static)) -> bool {
+    err.downcast_ref::<std::io::Error>()
+        .is_some_and(|err| err.kind() == std::io::ErrorKind::BrokenPipe)
+}
+
+/// Flags of a builtin that parses its arguments with clap, for completion.
+pub fn flags<Args: clap::CommandFactory>() -> Vec<String> {
+    let mut command = Args::command();
+    command.build();
+    let mut flags = Vec::new();
+    for arg in command.get_arguments() {
+        if let Some(long) = arg.get_long() {
+            flags.push(format!("--{long}"));
+        }
+        if let Some(short) = arg.get_short() {
+            flags.push(format!("-{short}"));
+        }
+    }
+    flags.sort();
+    flags
+}
@@ -1,23 +1,48 @@
-use crate::ir::BuiltinCommand;
-use std::{env::current_dir, error::Error};
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::{error::Error, fs};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Print the working directory with symbolic links kept (default).
+    #[arg(short = 'L', overrides_with = "physical")]
+    logical: bool,
+
+    /// Print the working directory with symbolic links resolved.
+    #[arg(short = 'P', overrides_with = "logical")]
+    physical: bool,
+}
 
 /// Implements the pwd built-in command.
 ///
-/// PwdCommand retrieves and prints the current working directory to the standard output.
+/// PwdCommand prints the working directory of the shell to the standard output.
 #[derive(Default, Debug)]
 pub struct PwdCommand;
 
 impl BuiltinCommand for PwdCommand {
     fn exec(
         &self,
-        _args: Vec<String>,
+        args: Vec<String>,
+        shell: &mut Shell,
         _stdin: &mut dyn std::io::Read,
         _stderr: &mut dyn std::io::Write,
         stdout: &mut dyn std::io::Write,
-    ) -> Result<(), Box<dyn Error + Sync + Send>> {
-        let path = current_dir()?;
-        write!(stdout, "{}", path.display())?;
-        Ok(())
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let mut path = shell.current_dir();
+        if args.physical {
+            path = fs::canonicalize(path)?;
+        }
+        writeln!(stdout, "{}", path.display())?;
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
     }
 
     fn tag(&self) -> &'static str {
@@ -0,0 +1,49 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    frontend::Control,
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Exit status of the function, that of the last command by default.
+    #[arg(allow_negative_numbers = true)]
+    code: Option<i32>,
+}
+
+/// Implements the return built-in command.
+///
+/// ReturnCommand ends the running function, skipping the rest of its body.
+#[derive(Default, Debug)]
+pub struct ReturnCommand;
+
+impl BuiltinCommand for ReturnCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let env = shell.frontend.env_mut();
+        if !env.in_function() {
+            return Err("return: can only be used in a function".into());
+        }
+        env.set_control(Some(Control::Return));
+        let code = args.code.unwrap_or(env.status()).rem_euclid(256);
+        Ok(ExitStatus::new(Some(code)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "return"
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    frontend::Environment,
+    ir::BuiltinCommand,
+};
+use std::error::Error;
+
+/// Options of `set -o`, the editing modes `emacs` and `vi` exclude each other.
+const OPTIONS: [&str; 5] = ["emacs", "failglob", "noglob", "nullglob", "vi"];
+
+/// Implements the set built-in command.
+///
+/// SetCommand prints every shell variable without arguments, enables and disables
+/// shell options with `-o name` and `+o name`, and replaces the positional
+/// parameters with its other arguments. Arguments are parsed by hand, as
+/// `set a -b` must not treat `-b` as an option.
+#[derive(Default, Debug)]
+pub struct SetCommand;
+
+impl BuiltinCommand for SetCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let mut args = args.into_iter().skip(1).peekable();
+        let env = shell.frontend.env_mut();
+        if args.peek().is_none() {
+            for (name, value) in env.variables() {
+                writeln!(stdout, "{name}={}", Environment::quote(value))?;
+            }
+            return Ok(ExitStatus::new(Some(0)));
+        }
+        while let Some(flag @ ("-o" | "+o")) = args.peek().map(String::as_str) {
+            let enable = flag == "-o";
+            args.next();
+            let Some(name) = args.next() else {
+                for name in OPTIONS {
+                    match enable {
+                        true => writeln!(stdout, "{name:<15} {}", on_off(env.option(name)))?,
+                        false if env.option(name) => writeln!(stdout, "set -o {name}")?,
+                        false => writeln!(stdout, "set +o {name}")?,
+                    }
+                }
+                return Ok(ExitStatus::new(Some(0)));
+            };
+            match name.as_str() {
+                "emacs" | "vi" => {
+                    for option in ["emacs", "vi"] {
+                        env.set_option(option, (option == name) == enable);
+                    }
+                }
+                name if OPTIONS.contains(&name) => env.set_option(name, enable),
+                _ => return Err(format!("set: {name}: invalid option name").into()),
+            }
+            if args.peek().is_none() {
+                return Ok(ExitStatus::new(Some(0)));
+            }
+        }
+        match args.peek().map(String::as_str) {
+            Some("--") => {
+                args.next();
+            }
+            Some(option) if option.starts_with(['-', '+']) && option.len() > 1 => {
+                return Err(format!("set: {option}: options are not supported").into());
+            }
+            Some(_) | None => {}
+        }
+        env.set_positional(args.collect());
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        ["-o", "+o", "--"].map(String::from).to_vec()
+    }
+
+    fn tag(&self) -> &'static str {
+        "set"
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    match enabled {
+        true => "on",
+        false => "off",
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Number of positional parameters to remove.
+    #[arg(default_value_t = 1)]
+    n: usize,
+}
+
+/// Implements the shift built-in command.
+///
+/// ShiftCommand removes the first positional parameters, renumbering the rest.
+#[derive(Default, Debug)]
+pub struct ShiftCommand;
+
+impl BuiltinCommand for ShiftCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        if !shell.frontend.env_mut().shift(args.n) {
+            return Err("shift: shift count out of range".into());
+        }
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "shift"
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Remove every alias.
+    #[arg(short = 'a')]
+    all: bool,
+
+    /// Aliases to remove.
+    #[arg(required_unless_present = "all")]
+    names: Vec<String>,
+}
+
+/// Implements the unalias built-in command.
+///
+/// UnaliasCommand removes aliases defined with `alias`.
+#[derive(Default, Debug)]
+pub struct UnaliasCommand;
+
+impl BuiltinCommand for UnaliasCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let env = shell.frontend.env_mut();
+
+        let mut names = args.names;
+        if args.all {
+            names.extend(env.aliases().map(|(name, _)| name.to_string()));
+        }
+        let mut code = 0;
+        for name in names {
+            if !env.unset_alias(&name) && !args.all {
+                writeln!(stderr, "unalias: {name}: not found")?;
+                code = 1;
+            }
+        }
+        Ok(ExitStatus::new(Some(code)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "unalias"
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Treat names as variables (default).
+    #[arg(short = 'v')]
+    variables: bool,
+
+    names: Vec<String>,
+}
+
+/// Implements the unset built-in command.
+///
+/// UnsetCommand removes shell variables, unsetting them in the environment
+/// of executed commands as well.
+#[derive(Default, Debug)]
+pub struct UnsetCommand;
+
+impl BuiltinCommand for UnsetCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        _stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        let env = shell.frontend.env_mut();
+        for name in args.names {
+            env.unset(&name);
+        }
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "unset"
+    }
+}
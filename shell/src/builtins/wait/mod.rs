@@ -0,0 +1,65 @@
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
+use clap::Parser;
+use std::error::Error;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Process ids or job specs to wait for, all jobs by default.
+    ids: Vec<String>,
+}
+
+/// Implements the wait built-in command.
+///
+/// WaitCommand waits until the given jobs are done and returns the exit status
+/// of the last one.
+#[derive(Default, Debug)]
+pub struct WaitCommand;
+
+impl BuiltinCommand for WaitCommand {
+    fn exec(
+        &self,
+        args: Vec<String>,
+        shell: &mut Shell,
+        _stdin: &mut dyn std::io::Read,
+        stderr: &mut dyn std::io::Write,
+        _stdout: &mut dyn std::io::Write,
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let args = Args::try_parse_from(args)?;
+        if args.ids.is_empty() {
+            let ids = shell.backend.jobs().ids();
+            for id in ids {
+                shell.backend.wait_job(id);
+            }
+            return Ok(ExitStatus::new(Some(0)));
+        }
+
+        let mut status = ExitStatus::new(Some(0));
+        for spec in &args.ids {
+            let id = shell.backend.jobs().find(spec);
+            status = match id.ok().and_then(|id| shell.backend.wait_job(id)) {
+                Some(status) => status,
+                None => {
+                    if spec.starts_with('%') {
+                        writeln!(stderr, "wait: {spec}: no such job")?;
+                    } else {
+                        writeln!(stderr, "wait: pid {spec} is not a child of this shell")?;
+                    }
+                    ExitStatus::new(Some(127))
+                }
+            };
+        }
+        Ok(status)
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
+    }
+
+    fn tag(&self) -> &'static str {
+        "wait"
+    }
+}
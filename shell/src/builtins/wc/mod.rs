@@ -1,6 +1,9 @@
 use std::{error::Error, fs::File, io::BufReader};
 
-use crate::ir::BuiltinCommand;
+use crate::{
+    backend::{ExitStatus, Shell},
+    ir::BuiltinCommand,
+};
 use clap::Parser;
 use counter_scope::CounterScope;
 use counters::{ByteCounter, CharacterCounter, MaxLineLengthCounter, NewlineCounter, WordCounter};
@@ -78,11 +81,12 @@ impl BuiltinCommand for WcCommand {
     fn exec(
         &self,
         args: Vec<String>,
+        shell: &mut Shell,
         stdin: &mut dyn std::io::Read,
         _stderr: &mut dyn std::io::Write,
         stdout: &mut dyn std::io::Write,
-    ) -> Result<(), Box<dyn Error + Sync + Send>> {
-        let mut args = Args::try_parse_from(args.into_iter())?;
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>> {
+        let mut args = Args::try_parse_from(args)?;
         let mut scope = CounterScope::from(&args);
         let mut stat_table = StatTable::default();
 
@@ -96,7 +100,7 @@ impl BuiltinCommand for WcCommand {
             let file = match path.as_str() {
                 "-" => &mut *stdin,
                 path => {
-                    let f = File::open(path)?;
+                    let f = File::open(shell.resolve(path))?;
                     _file_binding = Some(f);
                     _file_binding.as_mut().unwrap()
                 }
@@ -115,7 +119,11 @@ impl BuiltinCommand for WcCommand {
         }
 
         writeln!(stdout, "{}", stat_table)?;
-        Ok(())
+        Ok(ExitStatus::new(Some(0)))
+    }
+
+    fn flags(&self) -> Vec<String> {
+        crate::builtins::flags::<Args>()
     }
 
     fn tag(&self) -> &'static str {
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    frontend::Environment,
+    ir::{BuiltinCommand, Command},
+};
+
+/// Characters that are escaped with a backslash in completed words.
+const SPECIAL: &str = " \t'\"\\$&;|<>()*?[]#~`{}!";
+
+/// Completions of the word before the cursor.
+#[derive(Debug, PartialEq)]
+pub struct Completion {
+    /// Position of the first character of the completed word in the line.
+    pub start: usize,
+    /// Replacements for the word, sorted.
+    pub candidates: Vec<String>,
+}
+
+/// Completes command names, file paths, variables and builtin flags, with the
+/// state of the shell at the time the prompt was shown.
+#[derive(Debug, Default)]
+pub struct Completer {
+    variables: Vec<String>,
+    functions: Vec<String>,
+    path: String,
+    cwd: PathBuf,
+}
+
+impl Completer {
+    pub fn new(env: &Environment, cwd: PathBuf) -> Self {
+        Self {
+            variables: env
+                .variables()
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            functions: env.function_names().map(String::from).collect(),
+            path: env.get("PATH"),
+            cwd,
+        }
+    }
+
+    /// Completes the word that ends at the end of `line`.
+    ///
+    /// The first word of a command completes to builtins, functions and
+    /// executables of `PATH`, words starting with `$` to variables, words starting with `-` to
+    /// the flags of a builtin, and every other word to file paths.
+    pub fn complete(&self, line: &str) -> Completion {
+        let chars: Vec<char> = line.chars().collect();
+        let mut start = chars.len();
+        while start > 0 {
+            let c = chars[start - 1];
+            let escaped = start >= 2 && chars[start - 2] == '\\';
+            if (c.is_whitespace() || ";|&<>(".contains(c)) && !escaped {
+                break;
+            }
+            start -= 1;
+        }
+        let before: String = chars[..start].iter().collect();
+        let word = unescape(&chars[start..].iter().collect::<String>());
+        let command = command_words(&before);
+
+        let candidates = if let Some(name) = word.strip_prefix("${") {
+            self.complete_variable(name, "${", "}")
+        } else if let Some(name) = word.strip_prefix('$') {
+            self.complete_variable(name, "$", "")
+        } else if command.is_none() && !word.contains('/') {
+            complete_command(&word, &self.functions, &self.path)
+        } else if let (Some(name), true) = (command, word.starts_with('-')) {
+            match Command::builtin(name) {
+                Some(builtin) => complete_flags(builtin.as_ref(), &word),
+                None => Vec::new(),
+            }
+        } else {
+            complete_path(&word, &self.cwd, command.is_none())
+        };
+        Completion { start, candidates }
+    }
+
+    fn complete_variable(&self, prefix: &str, open: &str, close: &str) -> Vec<String> {
+        self.variables
+            .iter()
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| format!("{open}{name}{close}"))
+            .collect()
+    }
+}
+
+/// The command of the last pipeline in `before`, or `None` if the word that
+/// follows is in command position. Prefix assignments are skipped.
+fn command_words(before: &str) -> Option<&str> {
+    let segment = before
+        .rsplit([';', '|', '&', '('])
+        .next()
+        .unwrap_or_default();
+    segment.split_whitespace().find(|word| {
+        !word
+            .split_once('=')
+            .is_some_and(|(name, _)| Environment::is_valid_name(name))
+    })
+}
+
+fn complete_command(prefix: &str, functions: &[String], path: &str) -> Vec<String> {
+    let mut names: BTreeSet<String> = Command::builtin_names()
+        .map(String::from)
+        .chain(functions.iter().cloned())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    for dir in path.split(':').filter(|dir| !dir.is_empty()) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(prefix) && is_executable(&entry.path()) {
+                names.insert(escape(&name));
+            }
+        }
+    }
+    names.into_iter().collect()
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+fn complete_flags(builtin: &dyn BuiltinCommand, prefix: &str) -> Vec<String> {
+    let mut flags: Vec<String> = builtin
+        .flags()
+        .into_iter()
+        .filter(|flag| flag.starts_with(prefix))
+        .collect();
+    flags.sort();
+    flags
+}
+
+/// Completes a path relative to `cwd`. Directories end with `/`, and only
+/// directories and executables are offered when `executables` is set.
+fn complete_path(word: &str, cwd: &Path, executables: bool) -> Vec<String> {
+    let (dir, prefix) = match word.rsplit_once('/') {
+        Some((dir, prefix)) => (format!("{dir}/"), prefix),
+        None => (String::new(), word),
+    };
+    let Ok(entries) = fs::read_dir(cwd.join(if dir.is_empty() { "." } else { &dir })) else {
+        return Vec::new();
+    };
+    let mut candidates = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            candidates.push(format!("{}/", escape(&format!("{dir}{name}"))));
+        } else if !executables || is_executable(&path) {
+            candidates.push(escape(&format!("{dir}{name}")));
+        }
+    }
+    candidates.sort();
+    candidates
+}
+
+fn escape(word: &str) -> String {
+    let mut escaped = String::new();
+    for c in word.chars() {
+        if SPECIAL.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn unescape(word: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Completer;
+    use crate::frontend::Environment;
+
+    #[test]
+    fn test_complete() -> std::io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("shell-complete-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub dir"))?;
+        std::fs::write(dir.join("sub dir/file.txt"), "")?;
+        std::fs::write(dir.join("notes"), "")?;
+
+        let mut env = Environment::new();
+        env.set("PATH", String::new());
+        env.set("HOSTNAME", String::new());
+        env.set("HOME", String::new());
+        let list = crate::frontend::parse_intermediate("greet() { echo hi; }").unwrap();
+        env.define_function(
+            String::from("greet"),
+            list.items[0].first.commands[0].clone(),
+        );
+        let completer = Completer::new(&env, dir.clone());
+        let complete = |line: &str| completer.complete(line).candidates;
+
+        assert_eq!(complete("ec"), ["echo"]);
+        assert_eq!(complete("gr"), ["greet", "grep"]);
+        assert_eq!(complete("A=1 ex"), ["exit", "export"]);
+        assert_eq!(complete("echo a | wc --w"), ["--words"]);
+        assert_eq!(complete("set -"), ["--", "-o"]);
+        assert_eq!(complete("cat s"), [r"sub\ dir/"]);
+        assert_eq!(complete(r"cat sub\ dir/"), [r"sub\ dir/file.txt"]);
+        assert_eq!(completer.complete(r"cat sub\ dir/f").start, 4);
+        assert_eq!(complete("echo $HO"), ["$HOME", "$HOSTNAME"]);
+        assert_eq!(complete("echo ${HOM"), ["${HOME}"]);
+
+        std::fs::remove_dir_all(dir)
+    }
+}
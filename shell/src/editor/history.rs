@@ -0,0 +1,142 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Lines entered in earlier prompts, oldest first.
+///
+/// With a file, the history is loaded from it and every added line is appended
+/// to it right away, so that it survives `exit` and other shells can pick it up.
+/// The file is cut back to its last `file_size` lines whenever it grows past them.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// `HISTSIZE`, the number of lines kept in memory.
+    size: usize,
+    file: Option<PathBuf>,
+    /// `HISTFILESIZE`, the number of lines kept in the file.
+    file_size: usize,
+    /// The number of lines in the file as far as this shell knows.
+    file_lines: usize,
+}
+
+impl History {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            ..Self::default()
+        }
+    }
+
+    /// Loads the history from `file`, truncating the file to its last
+    /// `file_size` lines.
+    pub fn with_file(size: usize, file: PathBuf, file_size: usize) -> io::Result<Self> {
+        let mut history = Self::new(size);
+        let kept = truncate_file(&file, file_size)?;
+        for line in &kept {
+            history.push(line);
+        }
+        history.file = Some(file);
+        history.file_size = file_size;
+        history.file_lines = kept.len();
+        Ok(history)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Adds a line unless it is blank or repeats the last one, and appends it
+    /// to the history file, truncating the file once it exceeds its size.
+    pub fn add(&mut self, line: &str) -> io::Result<()> {
+        if !self.push(line) {
+            return Ok(());
+        }
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if self.file_size == 0 {
+            return Ok(());
+        }
+        writeln!(
+            OpenOptions::new().create(true).append(true).open(file)?,
+            "{line}"
+        )?;
+        self.file_lines += 1;
+        if self.file_lines > self.file_size {
+            self.file_lines = truncate_file(file, self.file_size)?.len();
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return false;
+        }
+        self.entries.push(String::from(line));
+        let excess = self.entries.len().saturating_sub(self.size);
+        self.entries.drain(..excess);
+        true
+    }
+
+    /// Searches backwards from the entry before `before` for one containing `query`.
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+}
+
+/// Cuts `file` back to its last `size` lines and returns them. A missing file
+/// has no lines.
+fn truncate_file(file: &Path, size: usize) -> io::Result<Vec<String>> {
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    let lines: Vec<&str> = contents.lines().collect();
+    let kept = &lines[lines.len().saturating_sub(size)..];
+    if kept.len() < lines.len() {
+        fs::write(
+            file,
+            kept.iter()
+                .map(|line| format!("{line}\n"))
+                .collect::<String>(),
+        )?;
+    }
+    Ok(kept.iter().map(|line| String::from(*line)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test]
+    fn test_size_limits_and_file() -> std::io::Result<()> {
+        let file = std::env::temp_dir().join(format!("shell-history-{}", std::process::id()));
+        std::fs::write(&file, "a\nb\nc\nd\n")?;
+
+        let mut history = History::with_file(2, file.clone(), 3)?;
+        assert_eq!(std::fs::read_to_string(&file)?, "b\nc\nd\n");
+        assert_eq!((history.len(), history.get(0)), (2, Some("c")));
+
+        history.add("e")?;
+        history.add("e")?;
+        history.add(" ")?;
+        assert_eq!((history.get(0), history.get(1)), (Some("d"), Some("e")));
+        assert_eq!(std::fs::read_to_string(&file)?, "c\nd\ne\n");
+        assert_eq!(history.search("d", history.len()), Some(0));
+        assert_eq!(history.search("e", 1), None);
+
+        history.add("f")?;
+        assert_eq!(std::fs::read_to_string(&file)?, "d\ne\nf\n");
+
+        std::fs::remove_file(file)
+    }
+}
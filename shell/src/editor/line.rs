@@ -0,0 +1,142 @@
+/// Text of the line being edited with the cursor position, in characters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl Line {
+    pub fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        Self {
+            cursor: chars.len(),
+            chars,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        for c in text.chars() {
+            self.insert(c);
+        }
+    }
+
+    /// Moves the cursor to `position`, clamped to the end of the line.
+    pub fn move_to(&mut self, position: usize) {
+        self.cursor = position.min(self.chars.len());
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.move_to(self.cursor + 1);
+    }
+
+    /// The start of the word before the cursor.
+    pub fn word_start(&self) -> usize {
+        let mut position = self.cursor;
+        while position > 0 && !is_word_char(self.chars[position - 1]) {
+            position -= 1;
+        }
+        while position > 0 && is_word_char(self.chars[position - 1]) {
+            position -= 1;
+        }
+        position
+    }
+
+    /// The end of the word after the cursor.
+    pub fn word_end(&self) -> usize {
+        let mut position = self.cursor;
+        while position < self.chars.len() && !is_word_char(self.chars[position]) {
+            position += 1;
+        }
+        while position < self.chars.len() && is_word_char(self.chars[position]) {
+            position += 1;
+        }
+        position
+    }
+
+    /// The start of the next word, as moved to by `w` in vi.
+    pub fn next_word_start(&self) -> usize {
+        let mut position = self.cursor;
+        while position < self.chars.len() && is_word_char(self.chars[position]) {
+            position += 1;
+        }
+        while position < self.chars.len() && !is_word_char(self.chars[position]) {
+            position += 1;
+        }
+        position
+    }
+
+    /// Removes the text between the cursor and `position` and returns it.
+    /// The cursor ends up at the start of the removed range.
+    pub fn delete_to(&mut self, position: usize) -> String {
+        let position = position.min(self.chars.len());
+        let (start, end) = (self.cursor.min(position), self.cursor.max(position));
+        self.cursor = start;
+        self.chars.drain(start..end).collect()
+    }
+
+    pub fn delete_back(&mut self) {
+        self.delete_to(self.cursor.saturating_sub(1));
+    }
+
+    pub fn delete_forward(&mut self) {
+        self.delete_to(self.cursor + 1);
+    }
+
+    /// Replaces the whole text and moves the cursor to its end.
+    pub fn set(&mut self, text: &str) {
+        *self = Self::new(text);
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Line;
+
+    #[test]
+    fn test_word_movement_and_deletion() {
+        let mut line = Line::new("echo foo_bar  baz");
+        assert_eq!(line.word_start(), 14);
+        line.move_to(line.word_start());
+        line.move_to(line.word_start());
+        assert_eq!(line.cursor(), 5);
+        assert_eq!(line.word_end(), 12);
+        assert_eq!(line.next_word_start(), 14);
+
+        assert_eq!(line.delete_to(line.next_word_start()), "foo_bar  ");
+        assert_eq!(line.text(), "echo baz");
+        line.delete_back();
+        line.insert('-');
+        assert_eq!(line.text(), "echo-baz");
+        assert_eq!(line.delete_to(0), "echo-");
+        assert_eq!((line.text().as_str(), line.cursor()), ("baz", 0));
+    }
+}
@@ -0,0 +1,587 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::Print,
+    terminal::{self, Clear, ClearType},
+};
+
+use crate::frontend::Environment;
+pub use complete::Completer;
+use complete::Completion;
+pub use history::History;
+use line::Line;
+
+mod complete;
+mod history;
+mod line;
+pub mod prompt;
+
+/// Key bindings of the editor, selected with `set -o emacs` and `set -o vi`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Keymap {
+    #[default]
+    Emacs,
+    Vi,
+}
+
+/// Mode of the vi key bindings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ViMode {
+    Insert,
+    Normal,
+}
+
+/// What the editor does after handling a key.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Continue,
+    Accept,
+    /// Ctrl-D on an empty line.
+    Eof,
+    /// Ctrl-C, the line is discarded.
+    Cancel,
+    ClearScreen,
+    /// Shows the candidates of an ambiguous completion.
+    List(Vec<String>),
+}
+
+/// State of a Ctrl-R reverse search through the history.
+#[derive(Debug)]
+struct Search {
+    query: String,
+    /// Index of the history entry that matches the query.
+    found: Option<usize>,
+    /// The line before the search started, restored if it is aborted.
+    original: Line,
+}
+
+/// Line editor for interactive shells.
+///
+/// Reads a line from the terminal in raw mode, with cursor movement, emacs or vi
+/// key bindings, history navigation, Ctrl-R reverse search and Tab completion.
+#[derive(Debug)]
+pub struct Editor {
+    history: History,
+    keymap: Keymap,
+    line: Line,
+    /// Index of the history entry being edited, `history.len()` for a new line.
+    history_index: usize,
+    /// The new line, kept while moving through the history.
+    draft: String,
+    vi_mode: ViMode,
+    /// vi operator (`d`, `c` or `y`) waiting for its motion.
+    pending: Option<char>,
+    search: Option<Search>,
+    /// Text removed by the last kill command, inserted again with Ctrl-Y or `p`.
+    killed: String,
+    completer: Completer,
+}
+
+impl Editor {
+    pub fn new(history: History) -> Self {
+        Self {
+            history,
+            keymap: Keymap::default(),
+            line: Line::default(),
+            history_index: 0,
+            draft: String::new(),
+            vi_mode: ViMode::Insert,
+            pending: None,
+            search: None,
+            killed: String::new(),
+            completer: Completer::default(),
+        }
+    }
+
+    /// Creates an editor with the history configured by `HISTFILE`, `HISTSIZE`
+    /// and `HISTFILESIZE`. The history file defaults to `~/.shell_history`.
+    pub fn from_env(env: &Environment) -> Self {
+        let size = |name: &str| env.get(name).parse().unwrap_or(500);
+        let file = match env.lookup("HISTFILE") {
+            Some(file) => Some(PathBuf::from(file)).filter(|file| !file.as_os_str().is_empty()),
+            None => env
+                .lookup("HOME")
+                .map(|home| PathBuf::from(home).join(".shell_history")),
+        };
+        let history = match file {
+            Some(file) => History::with_file(size("HISTSIZE"), file, size("HISTFILESIZE"))
+                .unwrap_or_else(|err| {
+                    eprintln!("shell: history: {err}");
+                    History::new(size("HISTSIZE"))
+                }),
+            None => History::new(size("HISTSIZE")),
+        };
+        Self::new(history)
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn set_completer(&mut self, completer: Completer) {
+        self.completer = completer;
+    }
+
+    /// Shows `prompt` on stderr and reads a line including its newline.
+    ///
+    /// Returns `None` for Ctrl-D on an empty line and an error of kind
+    /// [`io::ErrorKind::Interrupted`] for Ctrl-C.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        self.line = Line::default();
+        self.history_index = self.history.len();
+        self.draft.clear();
+        self.vi_mode = ViMode::Insert;
+        self.pending = None;
+        self.search = None;
+
+        let mut out = io::stderr();
+        terminal::enable_raw_mode()?;
+        let result = self.edit(prompt, &mut out);
+        terminal::disable_raw_mode()?;
+
+        match result? {
+            Action::Accept => {
+                let line = self.line.text();
+                if let Err(err) = self.history.add(&line) {
+                    eprintln!("shell: history: {err}");
+                }
+                Ok(Some(line + "\n"))
+            }
+            Action::Eof => Ok(None),
+            _ => Err(io::ErrorKind::Interrupted.into()),
+        }
+    }
+
+    fn edit(&mut self, prompt: &str, out: &mut impl Write) -> io::Result<Action> {
+        let (head, tail) = prompt.rsplit_once('\n').unwrap_or(("", prompt));
+        if !head.is_empty() {
+            write!(out, "{}\r\n", head.replace('\n', "\r\n"))?;
+        }
+        self.render(tail, out)?;
+        loop {
+            let action = match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => self.handle(key),
+                Event::Paste(text) => {
+                    self.line.insert_str(&text);
+                    Action::Continue
+                }
+                _ => Action::Continue,
+            };
+            match action {
+                Action::Continue => self.render(tail, out)?,
+                Action::ClearScreen => {
+                    queue!(out, Clear(ClearType::All), crossterm::cursor::MoveTo(0, 0))?;
+                    if !head.is_empty() {
+                        write!(out, "{}\r\n", head.replace('\n', "\r\n"))?;
+                    }
+                    self.render(tail, out)?;
+                }
+                Action::List(candidates) => {
+                    self.render(tail, out)?;
+                    write!(out, "\r\n{}\r\n", candidates.join("  "))?;
+                    if !head.is_empty() {
+                        write!(out, "{}\r\n", head.replace('\n', "\r\n"))?;
+                    }
+                    self.render(tail, out)?;
+                }
+                Action::Cancel => {
+                    write!(out, "^C\r\n")?;
+                    return Ok(action);
+                }
+                action => {
+                    self.search = None;
+                    self.render(tail, out)?;
+                    write!(out, "\r\n")?;
+                    return Ok(action);
+                }
+            }
+        }
+    }
+
+    /// Redraws the last line of the prompt and the edited line.
+    fn render(&self, prompt: &str, out: &mut impl Write) -> io::Result<()> {
+        let (prompt, text, cursor) = match &self.search {
+            Some(search) => {
+                let prompt = format!("(reverse-i-search)'{}': ", search.query);
+                let text = match search.found {
+                    Some(index) => self.history.get(index).unwrap_or_default().to_string(),
+                    None => String::new(),
+                };
+                (prompt, text, 0)
+            }
+            None => (prompt.to_string(), self.line.text(), self.line.cursor()),
+        };
+        let column = prompt.chars().count() + cursor;
+        queue!(
+            out,
+            MoveToColumn(0),
+            Print(prompt),
+            Print(text),
+            Clear(ClearType::UntilNewLine),
+            MoveToColumn(column as u16)
+        )?;
+        out.flush()
+    }
+
+    fn handle(&mut self, key: KeyEvent) -> Action {
+        if self.search.is_some() {
+            return self.handle_search(key);
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Action::Accept,
+            KeyCode::Char('j' | 'm') if ctrl => return Action::Accept,
+            KeyCode::Char('c') if ctrl => return Action::Cancel,
+            KeyCode::Char('d') if ctrl && self.line.is_empty() => return Action::Eof,
+            KeyCode::Char('l') if ctrl => return Action::ClearScreen,
+            KeyCode::Char('r') if ctrl => self.start_search(),
+            KeyCode::Up => self.history_previous(),
+            KeyCode::Down => self.history_next(),
+            KeyCode::Left => self.line.move_left(),
+            KeyCode::Right => self.line.move_right(),
+            KeyCode::Home => self.line.move_to(0),
+            KeyCode::End => self.line.move_to(self.line.len()),
+            KeyCode::Delete => self.line.delete_forward(),
+            KeyCode::Tab if self.keymap == Keymap::Emacs || self.vi_mode == ViMode::Insert => {
+                return self.complete();
+            }
+            _ => match (self.keymap, self.vi_mode) {
+                (Keymap::Emacs, _) | (Keymap::Vi, ViMode::Insert) => self.handle_insert(key),
+                (Keymap::Vi, ViMode::Normal) => self.handle_vi_normal(key),
+            },
+        }
+        if self.keymap == Keymap::Vi && self.vi_mode == ViMode::Normal {
+            self.line
+                .move_to(self.line.cursor().min(self.line.len().saturating_sub(1)));
+        }
+        Action::Continue
+    }
+
+    /// Keys of the emacs bindings, also used in the insert mode of vi.
+    fn handle_insert(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let emacs = self.keymap == Keymap::Emacs;
+        match key.code {
+            KeyCode::Esc if !emacs => {
+                self.vi_mode = ViMode::Normal;
+                self.line.move_left();
+            }
+            KeyCode::Backspace if alt => self.kill(self.line.word_start()),
+            KeyCode::Backspace => self.line.delete_back(),
+            KeyCode::Char('h') if ctrl => self.line.delete_back(),
+            KeyCode::Char('w') if ctrl => self.kill(self.line.word_start()),
+            KeyCode::Char('u') if ctrl => self.kill(0),
+            KeyCode::Char('y') if ctrl => self.line.insert_str(&self.killed.clone()),
+            KeyCode::Char('a') if ctrl && emacs => self.line.move_to(0),
+            KeyCode::Char('e') if ctrl && emacs => self.line.move_to(self.line.len()),
+            KeyCode::Char('b') if ctrl && emacs => self.line.move_left(),
+            KeyCode::Char('f') if ctrl && emacs => self.line.move_right(),
+            KeyCode::Char('d') if ctrl && emacs => self.line.delete_forward(),
+            KeyCode::Char('k') if ctrl && emacs => self.kill(self.line.len()),
+            KeyCode::Char('p') if ctrl && emacs => self.history_previous(),
+            KeyCode::Char('n') if ctrl && emacs => self.history_next(),
+            KeyCode::Char('b') if alt && emacs => self.line.move_to(self.line.word_start()),
+            KeyCode::Char('f') if alt && emacs => self.line.move_to(self.line.word_end()),
+            KeyCode::Char('d') if alt && emacs => self.kill(self.line.word_end()),
+            KeyCode::Char(c) if !ctrl && !alt => self.line.insert(c),
+            _ => {}
+        }
+    }
+
+    /// Keys of the normal mode of vi.
+    fn handle_vi_normal(&mut self, key: KeyEvent) {
+        let KeyCode::Char(c) = key.code else {
+            if key.code == KeyCode::Backspace {
+                self.line.move_left();
+            }
+            return;
+        };
+        if let Some(operator) = self.pending.take() {
+            let target = match c {
+                _ if c == operator => {
+                    self.line.move_to(0);
+                    Some(self.line.len())
+                }
+                'w' if operator == 'c' => Some(self.line.word_end()),
+                'w' => Some(self.line.next_word_start()),
+                'e' => Some(self.line.word_end()),
+                'b' => Some(self.line.word_start()),
+                '0' | '^' => Some(0),
+                '$' => Some(self.line.len()),
+                'l' => Some(self.line.cursor() + 1),
+                'h' => Some(self.line.cursor().saturating_sub(1)),
+                _ => None,
+            };
+            if let Some(target) = target {
+                match operator {
+                    'y' => {
+                        let cursor = self.line.cursor();
+                        self.killed = self.line.delete_to(target);
+                        let start = self.line.cursor();
+                        self.line.insert_str(&self.killed.clone());
+                        self.line.move_to(start.min(cursor));
+                    }
+                    _ => self.kill(target),
+                }
+                if operator == 'c' {
+                    self.vi_mode = ViMode::Insert;
+                }
+            }
+            return;
+        }
+        match c {
+            'h' => self.line.move_left(),
+            'l' | ' ' => self.line.move_right(),
+            '0' | '^' => self.line.move_to(0),
+            '$' => self.line.move_to(self.line.len()),
+            'w' => self.line.move_to(self.line.next_word_start()),
+            'b' => self.line.move_to(self.line.word_start()),
+            'e' => self.line.move_to(self.line.word_end().saturating_sub(1)),
+            'x' => self.kill(self.line.cursor() + 1),
+            'X' => self.kill(self.line.cursor().saturating_sub(1)),
+            'D' => self.kill(self.line.len()),
+            'd' | 'c' | 'y' => self.pending = Some(c),
+            'p' => {
+                self.line.move_right();
+                self.line.insert_str(&self.killed.clone());
+                self.line.move_left();
+            }
+            'P' => {
+                self.line.insert_str(&self.killed.clone());
+                self.line.move_left();
+            }
+            'k' => self.history_previous(),
+            'j' => self.history_next(),
+            '/' => self.start_search(),
+            'i' | 'a' | 'I' | 'A' | 'C' | 'S' => {
+                match c {
+                    'a' => self.line.move_right(),
+                    'I' => self.line.move_to(0),
+                    'A' => self.line.move_to(self.line.len()),
+                    'C' => self.kill(self.line.len()),
+                    'S' => {
+                        self.line.move_to(0);
+                        self.kill(self.line.len());
+                    }
+                    _ => {}
+                }
+                self.vi_mode = ViMode::Insert;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_search(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let Some(search) = &mut self.search else {
+            return Action::Continue;
+        };
+        match key.code {
+            KeyCode::Char('r') if ctrl => {
+                let before = search.found.unwrap_or(self.history.len());
+                if let Some(found) = self.history.search(&search.query, before) {
+                    search.found = Some(found);
+                }
+            }
+            KeyCode::Char('g' | 'c') if ctrl => {
+                self.line = search.original.clone();
+                self.search = None;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                search.query.push(c);
+                let before = search.found.map_or(self.history.len(), |found| found + 1);
+                search.found = self.history.search(&search.query, before);
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.found = self.history.search(&search.query, self.history.len());
+            }
+            KeyCode::Esc => self.finish_search(),
+            _ => {
+                self.finish_search();
+                return self.handle(key);
+            }
+        }
+        Action::Continue
+    }
+
+    /// Replaces the word before the cursor with its completion, or with the
+    /// longest prefix of the candidates. Candidates that do not extend the word
+    /// are listed instead.
+    fn complete(&mut self) -> Action {
+        let before: String = self.line.text().chars().take(self.line.cursor()).collect();
+        let Completion { start, candidates } = self.completer.complete(&before);
+        let Some(first) = candidates.first() else {
+            return Action::Continue;
+        };
+        let mut common = first.clone();
+        for candidate in &candidates[1..] {
+            while !candidate.starts_with(&common) {
+                common.pop();
+            }
+        }
+        if candidates.len() == 1 && !common.ends_with('/') {
+            common.push(' ');
+        }
+        if candidates.len() > 1 && common.chars().count() <= self.line.cursor() - start {
+            return Action::List(candidates);
+        }
+        self.line.delete_to(start);
+        self.line.insert_str(&common);
+        Action::Continue
+    }
+
+    fn start_search(&mut self) {
+        self.search = Some(Search {
+            query: String::new(),
+            found: None,
+            original: self.line.clone(),
+        });
+    }
+
+    /// Ends the search, editing the entry it found.
+    fn finish_search(&mut self) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+        if let Some(found) = search.found {
+            if self.history_index == self.history.len() {
+                self.draft = search.original.text();
+            }
+            self.history_index = found;
+            self.line.set(self.history.get(found).unwrap_or_default());
+        }
+    }
+
+    /// Removes the text between the cursor and `position`, keeping it for yanking.
+    fn kill(&mut self, position: usize) {
+        let killed = self.line.delete_to(position);
+        if !killed.is_empty() {
+            self.killed = killed;
+        }
+    }
+
+    fn history_previous(&mut self) {
+        if self.history_index == 0 {
+            return;
+        }
+        if self.history_index == self.history.len() {
+            self.draft = self.line.text();
+        }
+        self.history_index -= 1;
+        self.line
+            .set(self.history.get(self.history_index).unwrap_or_default());
+    }
+
+    fn history_next(&mut self) {
+        if self.history_index >= self.history.len() {
+            return;
+        }
+        self.history_index += 1;
+        match self.history.get(self.history_index) {
+            Some(entry) => self.line.set(entry),
+            None => self.line.set(&self.draft.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Editor, History, Keymap};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn editor(entries: &[&str]) -> Editor {
+        let mut history = History::new(10);
+        for entry in entries {
+            history.add(entry).unwrap();
+        }
+        let mut editor = Editor::new(history);
+        editor.history_index = entries.len();
+        editor
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn keys(editor: &mut Editor, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\x1b' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            editor.handle(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn test_emacs_editing() {
+        let mut editor = editor(&[]);
+        keys(&mut editor, "echo hello world");
+        editor.handle(ctrl('w'));
+        editor.handle(ctrl('a'));
+        editor.handle(ctrl('k'));
+        assert_eq!(editor.line.text(), "");
+        editor.handle(ctrl('y'));
+        assert_eq!(editor.line.text(), "echo hello ");
+        assert_eq!(editor.handle(ctrl('j')), Action::Accept);
+    }
+
+    #[test]
+    fn test_vi_editing() {
+        let mut editor = editor(&[]);
+        editor.set_keymap(Keymap::Vi);
+        keys(&mut editor, "echo one two\x1b");
+        keys(&mut editor, "bdw0cwprintf\x1b");
+        assert_eq!(editor.line.text(), "printf one ");
+        keys(&mut editor, "$xp");
+        assert_eq!(editor.line.text(), "printf one ");
+        keys(&mut editor, "ddiecho\x1b");
+        assert_eq!(editor.line.text(), "echo");
+    }
+
+    #[test]
+    fn test_tab_completion() {
+        let mut editor = editor(&[]);
+        keys(&mut editor, "ec");
+        editor.handle(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(editor.line.text(), "echo ");
+        keys(&mut editor, "| e");
+        assert_eq!(
+            editor.handle(KeyEvent::from(KeyCode::Tab)),
+            Action::List(["echo", "env", "exit", "export"].map(String::from).to_vec())
+        );
+        keys(&mut editor, "x");
+        editor.handle(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(editor.line.text(), "echo | ex");
+    }
+
+    #[test]
+    fn test_history_and_search() {
+        let mut editor = editor(&["ls", "echo s", "pwd"]);
+        keys(&mut editor, "new");
+        editor.handle(KeyEvent::from(KeyCode::Up));
+        editor.handle(KeyEvent::from(KeyCode::Up));
+        assert_eq!(editor.line.text(), "echo s");
+        editor.handle(KeyEvent::from(KeyCode::Down));
+        editor.handle(KeyEvent::from(KeyCode::Down));
+        assert_eq!(editor.line.text(), "new");
+
+        editor.handle(ctrl('r'));
+        keys(&mut editor, "s");
+        assert_eq!(editor.search.as_ref().unwrap().found, Some(1));
+        editor.handle(ctrl('r'));
+        assert_eq!(
+            editor.handle(KeyEvent::from(KeyCode::Enter)),
+            Action::Accept
+        );
+        assert_eq!(editor.line.text(), "ls");
+    }
+}
@@ -0,0 +1,82 @@
+use crate::frontend::Environment;
+
+/// Expands the backslash escapes of a `PS1` or `PS2` prompt.
+///
+/// Supported are `\u` user, `\h` host name up to the first dot, `\H` host name,
+/// `\w` working directory with `HOME` shown as `~`, `\W` its last component,
+/// `\s` name of the shell, `\$` `#` for root and `$` otherwise, `\n` and `\\`.
+pub fn expand(prompt: &str, env: &Environment) -> String {
+    let mut expanded = String::new();
+    let mut chars = prompt.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('u') => expanded.push_str(&env.get("USER")),
+            Some('h') => expanded.push_str(hostname().split('.').next().unwrap_or_default()),
+            Some('H') => expanded.push_str(&hostname()),
+            Some('w') => expanded.push_str(&working_dir(env)),
+            Some('W') => {
+                let dir = working_dir(env);
+                match dir.rsplit_once('/') {
+                    Some((_, name)) if !name.is_empty() => expanded.push_str(name),
+                    _ => expanded.push_str(&dir),
+                }
+            }
+            Some('s') => {
+                let name = env.get("0");
+                expanded.push_str(name.rsplit('/').next().unwrap_or_default());
+            }
+            Some('$') if nix::unistd::geteuid().is_root() => expanded.push('#'),
+            Some('$') => expanded.push('$'),
+            Some('n') => expanded.push('\n'),
+            Some('\\') => expanded.push('\\'),
+            Some(c) => {
+                expanded.push('\\');
+                expanded.push(c);
+            }
+            None => expanded.push('\\'),
+        }
+    }
+    expanded
+}
+
+fn hostname() -> String {
+    nix::unistd::gethostname()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn working_dir(env: &Environment) -> String {
+    let pwd = env.get("PWD");
+    let home = env.get("HOME");
+    match pwd.strip_prefix(&home) {
+        Some(rest) if !home.is_empty() && (rest.is_empty() || rest.starts_with('/')) => {
+            format!("~{rest}")
+        }
+        _ => pwd,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+    use crate::frontend::Environment;
+
+    #[test]
+    fn test_expand() {
+        let mut env = Environment::new();
+        env.set("USER", String::from("me"));
+        env.set("HOME", String::from("/home/me"));
+        env.set("PWD", String::from("/home/me/src/shell"));
+        assert_eq!(
+            expand(r"\u:\w \W\n> \x\\", &env),
+            "me:~/src/shell shell\n> \\x\\"
+        );
+
+        env.set("PWD", String::from("/home/meh"));
+        assert_eq!(expand(r"\w", &env), "/home/meh");
+    }
+}
@@ -0,0 +1,412 @@
+//! Alias expansion of the first word of simple commands, which happens when
+//! a command is read, before any other expansion.
+
+use conch_parser::ast;
+
+use super::{env::Environment, parse_intermediate, Arg, CompoundArg, ParamOp, ParseError};
+use super::{ShellCommandInterm, StringArg};
+use crate::ir::{CommandList, CompoundCommand};
+
+/// A simple command as a list of words, the first of which is the name.
+struct Simple {
+    assignments: Vec<(String, Option<CompoundArg>)>,
+    words: Vec<CompoundArg>,
+    redirects: Vec<ast::Redirect<CompoundArg>>,
+}
+
+/// Expands the aliases of every simple command in `list`, also within compound
+/// commands, function bodies and command substitutions.
+///
+/// This happens once the list is read, so an alias defined by a command of
+/// the list applies only to lines read after it, as in other shells.
+///
+/// # Errors
+///
+/// Returns an error if the value of an alias fails to parse.
+pub fn expand_list(list: &mut CommandList, env: &Environment) -> Result<(), ParseError> {
+    expand_commands(list, env, &mut Vec::new())
+}
+
+/// Expands the aliases of the commands of `list` and of everything nested in
+/// them, except for the aliases in `active`.
+fn expand_commands(
+    list: &mut CommandList,
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<(), ParseError> {
+    for and_or in &mut list.items {
+        let pipelines =
+            std::iter::once(&mut and_or.first).chain(and_or.rest.iter_mut().map(|(_, p)| p));
+        for pipeline in pipelines {
+            let commands = std::mem::take(&mut pipeline.commands);
+            for command in commands {
+                let command = match command {
+                    ShellCommandInterm::Execute { .. } => {
+                        match expand_command(command, env, active)? {
+                            mut command @ ShellCommandInterm::Execute { .. } => {
+                                expand_nested(&mut command, env, active)?;
+                                command
+                            }
+                            // A group from an alias, expanded by `group`.
+                            group => group,
+                        }
+                    }
+                    mut command => {
+                        expand_nested(&mut command, env, active)?;
+                        command
+                    }
+                };
+                pipeline.commands.push(command);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Expands the aliases in the lists and words that `command` contains.
+fn expand_nested(
+    command: &mut ShellCommandInterm,
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<(), ParseError> {
+    match command {
+        ShellCommandInterm::Execute {
+            assignments,
+            name,
+            args,
+            redirects,
+        } => {
+            for value in assignments
+                .iter_mut()
+                .filter_map(|(_, value)| value.as_mut())
+            {
+                expand_word(value, env, active)?;
+            }
+            for word in std::iter::once(name).chain(args) {
+                expand_word(word, env, active)?;
+            }
+            expand_redirects(redirects, env, active)
+        }
+        ShellCommandInterm::Assign { value, .. } => match value {
+            Some(value) => expand_word(value, env, active),
+            None => Ok(()),
+        },
+        ShellCommandInterm::Compound { command, redirects } => {
+            expand_compound(command, env, active)?;
+            expand_redirects(redirects, env, active)
+        }
+        ShellCommandInterm::Function { body, .. } => expand_nested(body, env, active),
+    }
+}
+
+fn expand_compound(
+    command: &mut CompoundCommand,
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<(), ParseError> {
+    match command {
+        CompoundCommand::Brace(list) | CompoundCommand::Subshell(list) => {
+            expand_commands(list, env, active)
+        }
+        CompoundCommand::If {
+            conditionals,
+            else_branch,
+        } => {
+            for (condition, body) in conditionals {
+                expand_commands(condition, env, active)?;
+                expand_commands(body, env, active)?;
+            }
+            match else_branch {
+                Some(list) => expand_commands(list, env, active),
+                None => Ok(()),
+            }
+        }
+        CompoundCommand::Loop {
+            condition, body, ..
+        } => {
+            expand_commands(condition, env, active)?;
+            expand_commands(body, env, active)
+        }
+        CompoundCommand::For { words, body, .. } => {
+            for word in words.iter_mut().flatten() {
+                expand_word(word, env, active)?;
+            }
+            expand_commands(body, env, active)
+        }
+        CompoundCommand::Case { word, arms } => {
+            expand_word(word, env, active)?;
+            for (patterns, body) in arms {
+                for pattern in patterns {
+                    expand_word(pattern, env, active)?;
+                }
+                expand_commands(body, env, active)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn expand_redirects(
+    redirects: &mut [ast::Redirect<CompoundArg>],
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<(), ParseError> {
+    for redirect in redirects {
+        let (ast::Redirect::Read(_, word)
+        | ast::Redirect::Write(_, word)
+        | ast::Redirect::ReadWrite(_, word)
+        | ast::Redirect::Append(_, word)
+        | ast::Redirect::Clobber(_, word)
+        | ast::Redirect::Heredoc(_, word)
+        | ast::Redirect::DupRead(_, word)
+        | ast::Redirect::DupWrite(_, word)) = redirect;
+        expand_word(word, env, active)?;
+    }
+    Ok(())
+}
+
+/// Expands the aliases in the command substitutions of `word`.
+fn expand_word(
+    word: &mut CompoundArg,
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<(), ParseError> {
+    word.inner
+        .iter_mut()
+        .try_for_each(|arg| expand_arg(arg, env, active))
+}
+
+fn expand_arg(
+    arg: &mut Arg,
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<(), ParseError> {
+    match arg {
+        Arg::Command(list) => expand_commands(list, env, active),
+        Arg::String(StringArg::DoubleQuoted(args)) => args
+            .iter_mut()
+            .try_for_each(|arg| expand_arg(arg, env, active)),
+        Arg::Param(_, op) => match op {
+            ParamOp::Default(_, Some(word))
+            | ParamOp::Assign(_, Some(word))
+            | ParamOp::Error(_, Some(word))
+            | ParamOp::Alternative(_, Some(word))
+            | ParamOp::RemovePrefix(_, Some(word))
+            | ParamOp::RemoveSuffix(_, Some(word)) => expand_word(word, env, active),
+            ParamOp::Replace(_, pattern, replacement) => {
+                expand_word(pattern, env, active)?;
+                expand_word(replacement, env, active)
+            }
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Replaces the name of a simple command with the value of its alias.
+///
+/// Only unquoted names are replaced, and an alias is not expanded again within
+/// its own value, so that `alias ls='ls -F'` works. If a value ends with a
+/// blank, the word after it is checked for an alias as well. A value that is
+/// not a single simple command, like `cd /tmp && ls`, becomes a `{ ...; }`
+/// group in which the arguments are appended to the last command.
+///
+/// # Errors
+///
+/// Returns an error if the value of an alias fails to parse.
+fn expand_command(
+    command: ShellCommandInterm,
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<ShellCommandInterm, ParseError> {
+    let ShellCommandInterm::Execute {
+        assignments,
+        name,
+        args,
+        redirects,
+    } = command
+    else {
+        return Ok(command);
+    };
+    let mut simple = Simple {
+        assignments,
+        words: std::iter::once(name).chain(args).collect(),
+        redirects,
+    };
+    if let Some(group) = expand_at(&mut simple, 0, env, active)? {
+        return Ok(group);
+    }
+    let mut words = simple.words.into_iter();
+    let Some(name) = words.next() else {
+        // An alias with an empty value leaves only the assignments and redirections.
+        return Ok(ShellCommandInterm::Execute {
+            assignments: simple.assignments,
+            name: CompoundArg::new(Vec::new()),
+            args: Vec::new(),
+            redirects: simple.redirects,
+        });
+    };
+    Ok(ShellCommandInterm::Execute {
+        assignments: simple.assignments,
+        name,
+        args: words.collect(),
+        redirects: simple.redirects,
+    })
+}
+
+/// Expands the alias of the word at `index` in place. Returns the group that
+/// replaces the whole command if the value is not a simple command.
+fn expand_at(
+    simple: &mut Simple,
+    index: usize,
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<Option<ShellCommandInterm>, ParseError> {
+    let Some(name) = simple.words.get(index).and_then(literal) else {
+        return Ok(None);
+    };
+    let Some(value) = env.alias(&name).filter(|_| !active.contains(&name)) else {
+        return Ok(None);
+    };
+    let list = parse_intermediate(value).map_err(|err| format!("{name}: {err}"))?;
+    let Some(replacement) = single_simple(&list) else {
+        if index > 0 {
+            return Ok(None);
+        }
+        active.push(name);
+        let group = group(list, simple, env, active);
+        active.pop();
+        return group.map(Some);
+    };
+
+    let length = simple.words.len();
+    let count = replacement.words.len();
+    simple.words.splice(index..=index, replacement.words);
+    simple.assignments.extend(replacement.assignments);
+    simple.redirects.extend(replacement.redirects);
+    if count > 0 {
+        active.push(name);
+        let group = expand_at(simple, index, env, active);
+        active.pop();
+        if let Some(group) = group? {
+            return Ok(Some(group));
+        }
+    }
+    if value.ends_with([' ', '\t']) {
+        let next = index + simple.words.len() + 1 - length;
+        return expand_at(simple, next, env, active);
+    }
+    Ok(None)
+}
+
+/// The text of a word without quotes or expansions.
+fn literal(word: &CompoundArg) -> Option<String> {
+    match &word.inner[..] {
+        [Arg::String(StringArg::Simple(text))] => Some(text.clone()),
+        _ => None,
+    }
+}
+
+/// The command of a list that consists of one simple command.
+fn single_simple(list: &CommandList) -> Option<Simple> {
+    let [and_or] = &list.items[..] else {
+        return None;
+    };
+    if and_or.background || !and_or.rest.is_empty() || and_or.first.negated {
+        return None;
+    }
+    let [ShellCommandInterm::Execute {
+        assignments,
+        name,
+        args,
+        redirects,
+    }] = &and_or.first.commands[..]
+    else {
+        return None;
+    };
+    Some(Simple {
+        assignments: assignments.clone(),
+        words: std::iter::once(name.clone()).chain(args.clone()).collect(),
+        redirects: redirects.clone(),
+    })
+}
+
+/// Turns the value of an alias into a `{ ...; }` group. The assignments of
+/// the command go to the first command of the value, its arguments and
+/// redirections to the last one.
+///
+/// The commands of the value are expanded right away, while the alias is
+/// still active.
+fn group(
+    mut list: CommandList,
+    simple: &mut Simple,
+    env: &Environment,
+    active: &mut Vec<String>,
+) -> Result<ShellCommandInterm, ParseError> {
+    let first = list
+        .items
+        .first_mut()
+        .and_then(|and_or| and_or.first.commands.first_mut());
+    if let Some(ShellCommandInterm::Execute { assignments, .. }) = first {
+        assignments.splice(0..0, simple.assignments.drain(..));
+    }
+    let last = list.items.last_mut().and_then(|and_or| {
+        let pipeline = and_or.rest.last_mut().map_or(&mut and_or.first, |(_, p)| p);
+        pipeline.commands.last_mut()
+    });
+    match last {
+        Some(ShellCommandInterm::Execute {
+            args, redirects, ..
+        }) => {
+            args.extend(simple.words.drain(1..));
+            redirects.append(&mut simple.redirects);
+        }
+        _ if simple.words.len() > 1 || !simple.redirects.is_empty() => {
+            return Err("alias value cannot take arguments".into());
+        }
+        _ => {}
+    }
+
+    expand_commands(&mut list, env, active)?;
+    Ok(ShellCommandInterm::Compound {
+        command: Box::new(CompoundCommand::Brace(list)),
+        redirects: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_list;
+    use crate::frontend::{env::Environment, parse_intermediate};
+
+    fn expand_line(input: &str, env: &Environment) -> String {
+        let mut list = parse_intermediate(input).unwrap();
+        expand_list(&mut list, env).unwrap();
+        list.to_string()
+    }
+
+    #[test]
+    fn test_alias_expansion() {
+        let mut env = Environment::new();
+        env.set_alias("ll", "ls -l");
+        env.set_alias("ls", "ls -F");
+        env.set_alias("sudo", "sudo ");
+        env.set_alias("a", "b");
+        env.set_alias("b", "a x");
+        env.set_alias("up", "cd .. && ll");
+
+        assert_eq!(expand_line("ll dir >out", &env), "ls -F -l dir >out");
+        assert_eq!(expand_line("sudo ll", &env), "sudo ls -F -l");
+        assert_eq!(expand_line("a y", &env), "a x y");
+        assert_eq!(expand_line("'ll' \\ll x", &env), "'ll' 'l'l x");
+        assert_eq!(expand_line("echo ll", &env), "echo ll");
+        assert_eq!(expand_line("up -a", &env), "{ cd .. && ls -F -l -a; }");
+        assert_eq!(
+            expand_line("f() { ll; }; echo $(sudo ll)", &env),
+            "f() { ls -F -l; }; echo $(sudo ls -F -l)"
+        );
+
+        env.set_alias("ls", "if true; then ls -F; fi");
+        assert_eq!(expand_line("ls", &env), "{ if true; then ls -F; fi; }");
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt::{self, Display};
+
+use conch_parser::{
+    ast::{Arithmetic, DefaultArithmetic},
+    lexer::Lexer,
+    parse::DefaultParser,
+};
+
+use super::{Environment, ParseError};
+
+/// How deep variables whose values are expressions themselves are evaluated.
+const MAX_DEPTH: usize = 32;
+
+/// Parses an arithmetic expression, as written inside `$(( ))`.
+pub fn parse(input: &str) -> Result<DefaultArithmetic, ParseError> {
+    let syntax_error = || ParseError::from(format!("{input}: syntax error in expression"));
+    let mut parser = DefaultParser::new(Lexer::new(input.chars()));
+    let expr = parser
+        .arithmetic_substitution()
+        .map_err(|_| syntax_error())?;
+    parser.skip_whitespace();
+    match parser.complete_command() {
+        Ok(None) => Ok(expr),
+        _ => Err(syntax_error()),
+    }
+}
+
+/// Evaluates an arithmetic expression with 64-bit integers.
+///
+/// Unset and empty variables are 0, variables that do not hold a number are
+/// evaluated as expressions. Assignments change variables in `env`.
+pub fn eval(expr: &DefaultArithmetic, env: &mut Environment) -> Result<i64, ParseError> {
+    Evaluator { env, depth: 0 }.eval(expr)
+}
+
+struct Evaluator<'a> {
+    env: &'a mut Environment,
+    depth: usize,
+}
+
+impl Evaluator<'_> {
+    fn eval(&mut self, expr: &DefaultArithmetic) -> Result<i64, ParseError> {
+        use Arithmetic::*;
+
+        let value = match expr {
+            Var(name) => self.var(name)?,
+            Literal(n) => *n as i64,
+            Pow(base, exp) => {
+                let (base, exp) = (self.eval(base)?, self.eval(exp)?);
+                let exp = u32::try_from(exp).map_err(|_| {
+                    ParseError::from(format!("{}: exponent less than 0", Expr(expr)))
+                })?;
+                base.wrapping_pow(exp)
+            }
+            PostIncr(name) | PreIncr(name) | PostDecr(name) | PreDecr(name) => {
+                let old = self.var(name)?;
+                let new = match expr {
+                    PostIncr(_) | PreIncr(_) => old.wrapping_add(1),
+                    _ => old.wrapping_sub(1),
+                };
+                self.assign(name, new);
+                match expr {
+                    PostIncr(_) | PostDecr(_) => old,
+                    _ => new,
+                }
+            }
+            UnaryPlus(expr) => self.eval(expr)?,
+            UnaryMinus(expr) => self.eval(expr)?.wrapping_neg(),
+            LogicalNot(expr) => (self.eval(expr)? == 0) as i64,
+            BitwiseNot(expr) => !self.eval(expr)?,
+            Mult(lhs, rhs) => self.eval(lhs)?.wrapping_mul(self.eval(rhs)?),
+            Div(lhs, rhs) | Modulo(lhs, rhs) => {
+                let (lhs, rhs) = (self.eval(lhs)?, self.eval(rhs)?);
+                if rhs == 0 {
+                    return Err(format!("{}: division by zero", Expr(expr)).into());
+                }
+                match expr {
+                    Div(..) => lhs.wrapping_div(rhs),
+                    _ => lhs.wrapping_rem(rhs),
+                }
+            }
+            Add(lhs, rhs) => self.eval(lhs)?.wrapping_add(self.eval(rhs)?),
+            Sub(lhs, rhs) => self.eval(lhs)?.wrapping_sub(self.eval(rhs)?),
+            ShiftLeft(lhs, rhs) => self.eval(lhs)?.wrapping_shl(self.eval(rhs)? as u32),
+            ShiftRight(lhs, rhs) => self.eval(lhs)?.wrapping_shr(self.eval(rhs)? as u32),
+            Less(lhs, rhs) => (self.eval(lhs)? < self.eval(rhs)?) as i64,
+            LessEq(lhs, rhs) => (self.eval(lhs)? <= self.eval(rhs)?) as i64,
+            Great(lhs, rhs) => (self.eval(lhs)? > self.eval(rhs)?) as i64,
+            GreatEq(lhs, rhs) => (self.eval(lhs)? >= self.eval(rhs)?) as i64,
+            Eq(lhs, rhs) => (self.eval(lhs)? == self.eval(rhs)?) as i64,
+            NotEq(lhs, rhs) => (self.eval(lhs)? != self.eval(rhs)?) as i64,
+            BitwiseAnd(lhs, rhs) => self.eval(lhs)? & self.eval(rhs)?,
+            BitwiseXor(lhs, rhs) => self.eval(lhs)? ^ self.eval(rhs)?,
+            BitwiseOr(lhs, rhs) => self.eval(lhs)? | self.eval(rhs)?,
+            LogicalAnd(lhs, rhs) => (self.eval(lhs)? != 0 && self.eval(rhs)? != 0) as i64,
+            LogicalOr(lhs, rhs) => (self.eval(lhs)? != 0 || self.eval(rhs)? != 0) as i64,
+            Ternary(guard, body, els) => match self.eval(guard)? {
+                0 => self.eval(els)?,
+                _ => self.eval(body)?,
+            },
+            Assign(name, expr) => {
+                let value = self.eval(expr)?;
+                self.assign(name, value);
+                value
+            }
+            Sequence(exprs) => {
+                let mut value = 0;
+                for expr in exprs {
+                    value = self.eval(expr)?;
+                }
+                value
+            }
+        };
+        Ok(value)
+    }
+
+    fn var(&mut self, name: &str) -> Result<i64, ParseError> {
+        let value = self.env.get(name);
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(0);
+        }
+        if let Some(number) = parse_number(value) {
+            return Ok(number);
+        }
+        if self.depth == MAX_DEPTH {
+            return Err(format!("{name}: expression recursion level exceeded").into());
+        }
+        self.depth += 1;
+        let value = parse(value).and_then(|expr| self.eval(&expr));
+        self.depth -= 1;
+        value
+    }
+
+    fn assign(&mut self, name: &str, value: i64) {
+        self.env.set(name, value.to_string());
+    }
+}
+
+/// Parses a decimal, `0x` hexadecimal or `0` octal integer.
+fn parse_number(value: &str) -> Option<i64> {
+    if let Some(hex) = value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if value.len() > 1 && value.starts_with('0') {
+        i64::from_str_radix(&value[1..], 8).ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
+/// Displays an arithmetic expression, parenthesizing nested operations.
+pub struct Expr<'a>(pub &'a DefaultArithmetic);
+
+impl Display for Expr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Arithmetic::*;
+
+        let nested = |expr: &DefaultArithmetic| match expr {
+            Var(_) | Literal(_) | PostIncr(_) | PostDecr(_) | PreIncr(_) | PreDecr(_) => {
+                format!("{}", Expr(expr))
+            }
+            _ => format!("({})", Expr(expr)),
+        };
+        let (lhs, operator, rhs) = match self.0 {
+            Var(name) => return write!(f, "{name}"),
+            Literal(n) => return write!(f, "{n}"),
+            PostIncr(name) => return write!(f, "{name}++"),
+            PostDecr(name) => return write!(f, "{name}--"),
+            PreIncr(name) => return write!(f, "++{name}"),
+            PreDecr(name) => return write!(f, "--{name}"),
+            UnaryPlus(expr) => return write!(f, "+{}", nested(expr)),
+            UnaryMinus(expr) => return write!(f, "-{}", nested(expr)),
+            LogicalNot(expr) => return write!(f, "!{}", nested(expr)),
+            BitwiseNot(expr) => return write!(f, "~{}", nested(expr)),
+            Ternary(guard, body, els) => {
+                return write!(f, "{} ? {} : {}", nested(guard), nested(body), nested(els))
+            }
+            Assign(name, expr) => return write!(f, "{name} = {}", Expr(expr)),
+            Sequence(exprs) => {
+                let exprs: Vec<_> = exprs.iter().map(|expr| Expr(expr).to_string()).collect();
+                return write!(f, "{}", exprs.join(", "));
+            }
+            Pow(lhs, rhs) => (lhs, "**", rhs),
+            Mult(lhs, rhs) => (lhs, "*", rhs),
+            Div(lhs, rhs) => (lhs, "/", rhs),
+            Modulo(lhs, rhs) => (lhs, "%", rhs),
+            Add(lhs, rhs) => (lhs, "+", rhs),
+            Sub(lhs, rhs) => (lhs, "-", rhs),
+            ShiftLeft(lhs, rhs) => (lhs, "<<", rhs),
+            ShiftRight(lhs, rhs) => (lhs, ">>", rhs),
+            Less(lhs, rhs) => (lhs, "<", rhs),
+            LessEq(lhs, rhs) => (lhs, "<=", rhs),
+            Great(lhs, rhs) => (lhs, ">", rhs),
+            GreatEq(lhs, rhs) => (lhs, ">=", rhs),
+            Eq(lhs, rhs) => (lhs, "==", rhs),
+            NotEq(lhs, rhs) => (lhs, "!=", rhs),
+            BitwiseAnd(lhs, rhs) => (lhs, "&", rhs),
+            BitwiseXor(lhs, rhs) => (lhs, "^", rhs),
+            BitwiseOr(lhs, rhs) => (lhs, "|", rhs),
+            LogicalAnd(lhs, rhs) => (lhs, "&&", rhs),
+            LogicalOr(lhs, rhs) => (lhs, "||", rhs),
+        };
+        write!(f, "{} {operator} {}", nested(lhs), nested(rhs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc(input: &str, env: &mut Environment) -> Result<i64, ParseError> {
+        eval(&parse(input)?, env)
+    }
+
+    #[test]
+    fn test_eval_precedence_and_operators() {
+        let mut env = Environment::new();
+        assert_eq!(calc("1 + 2 * 3 - 4 / 2", &mut env), Ok(5));
+        assert_eq!(calc("(1 + 2) * 3 % 4", &mut env), Ok(1));
+        assert_eq!(calc("2 ** 3 ** 2", &mut env), Ok(512));
+        assert_eq!(calc("-7 / 2, -7 % 2", &mut env), Ok(-1));
+        assert_eq!(calc("1 << 4 | 3 & ~1 ^ 8", &mut env), Ok(26));
+        assert_eq!(calc("1 < 2 && 2 >= 3 || !0", &mut env), Ok(1));
+        assert_eq!(calc("0x10 + 010 + 1 ? 7 : 8", &mut env), Ok(7));
+        assert!(calc("1 +", &mut env).is_err());
+        assert!(calc("1 2", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_eval_variables() {
+        let mut env = Environment::new();
+        env.set("a", String::from("5"));
+        env.set("e", String::from("a * 2"));
+        assert_eq!(calc("b = a++ + ++a", &mut env), Ok(12));
+        assert_eq!(
+            (env.get("a"), env.get("b")),
+            (String::from("7"), String::from("12"))
+        );
+        assert_eq!(calc("b += 3, b -= e, b *= 2", &mut env), Ok(2));
+        assert_eq!(calc("unset + 1", &mut env), Ok(1));
+        assert_eq!(calc("0 && x++, x", &mut env), Ok(0));
+
+        env.set("loop", String::from("loop"));
+        assert!(calc("loop", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_eval_division_by_zero() {
+        let mut env = Environment::new();
+        assert_eq!(
+            calc("1 / (2 - 2)", &mut env),
+            Err(ParseError::from("1 / (2 - 2): division by zero"))
+        );
+        assert!(calc("5 % 0", &mut env).is_err());
+    }
+}
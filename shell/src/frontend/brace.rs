@@ -0,0 +1,200 @@
+//! Brace expansion of unquoted `{a,b}` and `{1..5}` in words, which happens
+//! before any other expansion.
+
+use super::{Arg, CompoundArg, StringArg};
+
+/// A character of unquoted text, or a part of the word that is quoted or expanded.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Unit {
+    Char(char),
+    Part(Arg),
+}
+
+/// Splits the unquoted literal text of `word` into characters.
+pub(super) fn units(word: CompoundArg) -> Vec<Unit> {
+    let mut units = Vec::new();
+    for part in word.inner {
+        match part {
+            Arg::String(StringArg::Simple(text)) => units.extend(text.chars().map(Unit::Char)),
+            Arg::Number(n) => units.extend(n.to_string().chars().map(Unit::Char)),
+            part => units.push(Unit::Part(part)),
+        }
+    }
+    units
+}
+
+/// Expands the braces of `word` into the words they stand for, from left to right.
+/// Words without valid braces are returned unchanged.
+pub fn expand(word: CompoundArg) -> Vec<CompoundArg> {
+    expand_units(units(word))
+        .into_iter()
+        .map(into_word)
+        .collect()
+}
+
+fn expand_units(units: Vec<Unit>) -> Vec<Vec<Unit>> {
+    for open in 0..units.len() {
+        if units[open] != Unit::Char('{') {
+            continue;
+        }
+        let Some((close, alternatives)) = alternatives(&units, open) else {
+            continue;
+        };
+        let mut words = Vec::new();
+        for alternative in alternatives {
+            let mut word = units[..open].to_vec();
+            word.extend(alternative);
+            word.extend_from_slice(&units[close + 1..]);
+            words.extend(expand_units(word));
+        }
+        return words;
+    }
+    vec![units]
+}
+
+/// Finds the `}` that closes the brace at `open` and what the braces stand for:
+/// the parts separated by top-level commas, or the elements of a sequence.
+fn alternatives(units: &[Unit], open: usize) -> Option<(usize, Vec<Vec<Unit>>)> {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut close = None;
+    for (index, unit) in units.iter().enumerate().skip(open + 1) {
+        match unit {
+            Unit::Char('{') => depth += 1,
+            Unit::Char('}') if depth == 0 => {
+                close = Some(index);
+                break;
+            }
+            Unit::Char('}') => depth -= 1,
+            Unit::Char(',') if depth == 0 => commas.push(index),
+            _ => {}
+        }
+    }
+    let close = close?;
+    if commas.is_empty() {
+        let text = units[open + 1..close]
+            .iter()
+            .map(|unit| match unit {
+                Unit::Char(c) => Some(*c),
+                Unit::Part(_) => None,
+            })
+            .collect::<Option<String>>()?;
+        let sequence = sequence(&text)?;
+        let sequence = sequence
+            .into_iter()
+            .map(|element| element.chars().map(Unit::Char).collect())
+            .collect();
+        return Some((close, sequence));
+    }
+    let mut bounds = vec![open];
+    bounds.extend(commas);
+    bounds.push(close);
+    let alternatives = bounds
+        .windows(2)
+        .map(|bound| units[bound[0] + 1..bound[1]].to_vec())
+        .collect();
+    Some((close, alternatives))
+}
+
+/// Elements of `start..end` or `start..end..step`, for integers or single letters.
+/// Integers are padded to the same width if either bound has a leading zero.
+fn sequence(text: &str) -> Option<Vec<String>> {
+    let bounds: Vec<&str> = text.split("..").collect();
+    let (start, end, step) = match bounds[..] {
+        [start, end] => (start, end, 1),
+        [start, end, step] => (start, end, step.parse::<i64>().ok()?.unsigned_abs().max(1)),
+        _ => return None,
+    };
+    let step = usize::try_from(step).ok()?;
+    if let (Ok(first), Ok(last)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        let padded = |bound: &str| {
+            bound.trim_start_matches('-').len() > 1
+                && bound.trim_start_matches('-').starts_with('0')
+        };
+        let width = match padded(start) || padded(end) {
+            true => start.len().max(end.len()),
+            false => 0,
+        };
+        let numbers: Vec<i64> = match first <= last {
+            true => (first..=last).step_by(step).collect(),
+            false => (last..=first).rev().step_by(step).collect(),
+        };
+        return Some(
+            numbers
+                .into_iter()
+                .map(|n| format!("{n:0width$}"))
+                .collect(),
+        );
+    }
+    let (mut first, mut last) = (start.chars(), end.chars());
+    let (Some(first), None, Some(last), None) =
+        (first.next(), first.next(), last.next(), last.next())
+    else {
+        return None;
+    };
+    if !first.is_ascii_alphabetic() || !last.is_ascii_alphabetic() {
+        return None;
+    }
+    let letters: Vec<char> = match first <= last {
+        true => (first..=last).step_by(step).collect(),
+        false => (last..=first).rev().step_by(step).collect(),
+    };
+    Some(letters.into_iter().map(String::from).collect())
+}
+
+/// Joins units back into a word.
+pub(super) fn into_word(units: Vec<Unit>) -> CompoundArg {
+    let mut inner = Vec::new();
+    let mut text = String::new();
+    for unit in units {
+        match unit {
+            Unit::Char(c) => text.push(c),
+            Unit::Part(part) => {
+                if !text.is_empty() {
+                    inner.push(Arg::String(StringArg::Simple(std::mem::take(&mut text))));
+                }
+                inner.push(part);
+            }
+        }
+    }
+    if !text.is_empty() || inner.is_empty() {
+        inner.push(Arg::String(StringArg::Simple(text)));
+    }
+    CompoundArg { inner }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+    use crate::frontend::{parse_intermediate, ShellCommandInterm};
+
+    fn expand_words(input: &str) -> Vec<String> {
+        let list = parse_intermediate(input).unwrap();
+        let ShellCommandInterm::Execute { args, .. } = &list.items[0].first.commands[0] else {
+            panic!("not a command");
+        };
+        args.iter()
+            .flat_map(|word| expand(word.clone()))
+            .map(|word| word.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_brace_expansion() {
+        assert_eq!(expand_words("echo a{b,c}d"), ["abd", "acd"]);
+        assert_eq!(expand_words("echo {a,b}{1,2}"), ["a1", "a2", "b1", "b2"]);
+        assert_eq!(expand_words("echo {a,{b,c}x}"), ["a", "bx", "cx"]);
+        assert_eq!(
+            expand_words("echo {1..3} {c..a}"),
+            ["1", "2", "3", "c", "b", "a"]
+        );
+        assert_eq!(
+            expand_words("echo {08..10} {10..0..5}"),
+            ["08", "09", "10", "10", "5", "0"]
+        );
+        assert_eq!(
+            expand_words("echo {a} {} x{1..b} '{a,b}' {$x,y}"),
+            ["{a}", "{}", "x{1..b}", "'{a,b}'", "$x", "y"]
+        );
+    }
+}
@@ -1,14 +1,116 @@
-use super::env::Environment;
-use crate::frontend::{Arg, CompoundArg, ParseError, ShellCommandInterm};
-use crate::ir::{CallCommand, Command, PipeCommand};
-use std::collections::HashMap;
+use super::{arith, brace, env::Environment, tilde};
+use crate::frontend::{
+    pattern, Arg, CompoundArg, ParamOp, ParseError, ShellCommandInterm, StringArg,
+};
+use crate::ir::{CallCommand, Command, CommandList, PipeCommand, Redirect};
+use conch_parser::ast;
 
 /// Compiler transforms inner shell command representation
 /// into executable commands with given environment
+#[derive(Clone)]
 pub struct Compiler {
     pub env: Environment,
 }
 
+/// A field being expanded, with the glob pattern it forms. Quoted text is
+/// escaped in the pattern.
+#[derive(Default)]
+struct Field {
+    text: String,
+    pattern: String,
+    /// Whether unquoted text contains pattern characters.
+    glob: bool,
+    /// Whether the field is kept even if it is empty, because it has a quoted
+    /// part or ends at a delimiter other than a blank.
+    keep: bool,
+}
+
+impl Field {
+    fn push(&mut self, text: &str, quoted: bool) {
+        self.text.push_str(text);
+        if quoted {
+            self.pattern.push_str(&glob::Pattern::escape(text));
+            self.keep = true;
+        } else {
+            self.pattern.push_str(text);
+            self.glob |= text.contains(['*', '?', '[']);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.is_empty() && !self.keep
+    }
+}
+
+/// The fields a word expands to, with the results of unquoted expansions split
+/// at the characters of `IFS`.
+///
+/// Blanks of `IFS`, i.e. space, tab and newline, delimit fields in runs and
+/// are ignored at the start and the end. Every other character of `IFS`
+/// delimits a field on its own, together with the blanks around it.
+struct Fields {
+    fields: Vec<Field>,
+    ifs: String,
+    /// Whether the last field ended at a blank, so that more text starts a new one.
+    blank: bool,
+}
+
+impl Fields {
+    fn new(ifs: String) -> Self {
+        Self {
+            fields: vec![Field::default()],
+            ifs,
+            blank: false,
+        }
+    }
+
+    /// Appends text that is not split.
+    fn push(&mut self, text: &str, quoted: bool) {
+        if self.blank && (quoted || !text.is_empty()) {
+            self.blank = false;
+            if !self.current().is_empty() {
+                self.fields.push(Field::default());
+            }
+        }
+        self.current().push(text, quoted);
+    }
+
+    /// Appends the result of an unquoted expansion, split into fields.
+    fn split(&mut self, text: &str) {
+        let mut run = String::new();
+        for c in text.chars() {
+            if !self.ifs.contains(c) {
+                run.push(c);
+                continue;
+            }
+            self.push(&std::mem::take(&mut run), false);
+            if matches!(c, ' ' | '\t' | '\n') {
+                self.blank = true;
+            } else {
+                self.blank = false;
+                self.current().keep = true;
+                self.next();
+            }
+        }
+        self.push(&run, false);
+    }
+
+    /// Ends the current field.
+    fn next(&mut self) {
+        self.blank = false;
+        self.fields.push(Field::default());
+    }
+
+    fn current(&mut self) -> &mut Field {
+        self.fields.last_mut().unwrap()
+    }
+
+    /// The fields without the empty ones that are not kept.
+    fn finish(self) -> impl Iterator<Item = Field> {
+        self.fields.into_iter().filter(|field| !field.is_empty())
+    }
+}
+
 impl Compiler {
     pub fn new() -> Self {
         Self {
@@ -16,41 +118,368 @@ impl Compiler {
         }
     }
 
-    pub fn compile(&mut self, interm: Vec<ShellCommandInterm>) -> Result<PipeCommand, ParseError> {
+    /// Compiles a pipeline, running command substitutions with `substitute`.
+    ///
+    /// Words are expanded with the environment from before the pipeline. Only
+    /// a pipeline of a single command keeps the variables assigned by its
+    /// arithmetic expansions, longer ones run in subshells.
+    ///
+    /// A pipeline without commands, like one of assignments only, leaves the
+    /// status of its last command substitution in `$?`, or success.
+    pub fn compile(
+        &mut self,
+        interm: Vec<ShellCommandInterm>,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<PipeCommand, ParseError> {
+        let mut status = 0;
+        let substitute = &mut |list| {
+            let (output, code) = substitute(list)?;
+            status = code;
+            Ok((output, code))
+        };
         let mut commands = Vec::new();
-        let env_copy = self.env.clone();
+        let single = interm.len() == 1;
+        let mut env_copy = self.env.clone();
         for command_interm in interm {
-            let arg_to_str = |arg: CompoundArg| {
-                let transformed_parts: Vec<String> = arg
-                    .inner
-                    .iter()
-                    .map(|p| match p {
-                        Arg::String(str) => str.inner(&env_copy),
-                        Arg::Var(name) => env_copy.get(name),
-                        Arg::Number(n) => n.to_string(),
-                    })
-                    .collect();
-                transformed_parts.join("")
-            };
+            let env = if single { &mut self.env } else { &mut env_copy };
             match command_interm {
-                ShellCommandInterm::Execute { name, args } => {
-                    let name = arg_to_str(name);
-                    let args: Vec<String> = args.into_iter().map(arg_to_str).collect();
-                    let mut argv = vec![name.clone()];
-                    argv.extend(args);
+                ShellCommandInterm::Execute {
+                    assignments,
+                    name,
+                    args,
+                    redirects,
+                } => {
+                    let words = std::iter::once(name).chain(args).collect();
+                    let argv = Self::expand_words(env, words, substitute)?;
+                    let assignments = assignments
+                        .into_iter()
+                        .map(|(name, value)| {
+                            let value = value.map(|value| tilde::expand(value, true, env));
+                            Ok((name, Self::expand_word(env, value, substitute)?))
+                        })
+                        .collect::<Result<Vec<_>, ParseError>>()?;
+                    let mut arg_to_str = |arg| {
+                        let arg = tilde::expand(arg, false, env);
+                        Self::expand_word(env, Some(arg), substitute)
+                    };
+                    let redirects = redirects
+                        .into_iter()
+                        .map(|redirect| Self::compile_redirect(redirect, &mut arg_to_str))
+                        .collect::<Result<Vec<Redirect>, ParseError>>()?;
+                    if argv.is_empty() {
+                        continue;
+                    }
+                    let env = if single { &self.env } else { &env_copy };
+                    let mut envs = env.exported();
+                    envs.extend(assignments);
                     commands.push(CallCommand {
-                        envs: HashMap::new(),
-                        command: Command::from_name(&name),
+                        envs,
+                        command: Command::from_argv(&argv, env),
                         argv,
+                        redirects,
                     })
                 }
                 ShellCommandInterm::Assign { name, value } => {
-                    let value = value.map_or(String::from(""), arg_to_str);
+                    let value = value.map(|value| tilde::expand(value, true, env));
+                    let value = Self::expand_word(env, value, substitute)?;
                     self.env.set(&name, value);
                 }
+                ShellCommandInterm::Compound { command, redirects } => {
+                    let mut arg_to_str = |arg| {
+                        let arg = tilde::expand(arg, false, env);
+                        Self::expand_word(env, Some(arg), substitute)
+                    };
+                    let redirects = redirects
+                        .into_iter()
+                        .map(|redirect| Self::compile_redirect(redirect, &mut arg_to_str))
+                        .collect::<Result<Vec<Redirect>, ParseError>>()?;
+                    commands.push(CallCommand {
+                        envs: if single { &self.env } else { &env_copy }.exported(),
+                        command: Command::Compound(command),
+                        argv: Vec::new(),
+                        redirects,
+                    })
+                }
+                ShellCommandInterm::Function { name, body } => env.define_function(name, *body),
             }
         }
 
+        if commands.is_empty() {
+            self.env.set_status(status);
+        }
         Ok(PipeCommand { commands })
     }
+
+    /// Expands words into fields the way arguments of a command are expanded,
+    /// as for the words of `for`.
+    pub fn expand_arguments(
+        &mut self,
+        words: Vec<CompoundArg>,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<Vec<String>, ParseError> {
+        Self::expand_words(&mut self.env, words, substitute)
+    }
+
+    /// Expands a word into a single string without pathname expansion, as for
+    /// the word of `case`.
+    pub fn expand_string(
+        &mut self,
+        word: CompoundArg,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<String, ParseError> {
+        let word = tilde::expand(word, false, &self.env);
+        Self::expand_word(&mut self.env, Some(word), substitute)
+    }
+
+    /// Expands a word into a pattern, as for the patterns of `case`.
+    pub fn expand_glob(
+        &mut self,
+        word: CompoundArg,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<glob::Pattern, ParseError> {
+        let word = tilde::expand(word, false, &self.env);
+        Self::expand_pattern(&mut self.env, Some(word), substitute)
+    }
+
+    /// Applies brace, tilde and parameter expansion, field splitting of `$@`
+    /// and pathname expansion to words.
+    fn expand_words(
+        env: &mut Environment,
+        words: Vec<CompoundArg>,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<Vec<String>, ParseError> {
+        let mut fields = Vec::new();
+        for word in words.into_iter().flat_map(brace::expand) {
+            let word = tilde::expand(word, false, env);
+            fields.extend(Self::expand_fields(env, word, substitute)?);
+        }
+        Ok(fields)
+    }
+
+    /// Expands a single part of a word. Trailing newlines of a command substitution are
+    /// removed and its status becomes `$?`.
+    fn expand(
+        env: &mut Environment,
+        arg: Arg,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<String, ParseError> {
+        let expanded = match arg {
+            Arg::String(StringArg::DoubleQuoted(inner)) => inner
+                .into_iter()
+                .map(|part| Self::expand(env, part, substitute))
+                .collect::<Result<String, ParseError>>()?,
+            Arg::String(StringArg::SingleQuoted(inner) | StringArg::Simple(inner)) => inner,
+            Arg::Var(name) => env.get(&name),
+            Arg::Number(n) => n.to_string(),
+            Arg::Param(name, op) => Self::expand_param(env, name, op, substitute)?,
+            Arg::Arith(None) => String::from("0"),
+            Arg::Arith(Some(expr)) => arith::eval(&expr, env)?.to_string(),
+            Arg::Command(list) => {
+                let (mut output, status) = substitute(list)?;
+                env.set_status(status);
+                output.truncate(output.trim_end_matches('\n').len());
+                output
+            }
+        };
+        Ok(expanded)
+    }
+
+    /// Expands a word into fields.
+    ///
+    /// Unquoted parameter expansions, command substitutions and arithmetic
+    /// expansions are split into fields at the characters of `IFS`, by default
+    /// space, tab and newline. `$@` results in a field for each positional
+    /// parameter. Fields that end up empty are removed unless they are quoted,
+    /// so a word of only `"$@"` disappears when there are none. Fields with
+    /// unquoted `*`, `?` or `[` are replaced by the paths they match.
+    fn expand_fields(
+        env: &mut Environment,
+        word: CompoundArg,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<Vec<String>, ParseError> {
+        let parts = word.inner.into_iter().flat_map(|part| match part {
+            Arg::String(StringArg::DoubleQuoted(inner)) => {
+                inner.into_iter().map(|part| (part, true)).collect()
+            }
+            part @ Arg::String(StringArg::SingleQuoted(_)) => vec![(part, true)],
+            part => vec![(part, false)],
+        });
+        let ifs = env.lookup("IFS").unwrap_or_else(|| String::from(" \t\n"));
+        let mut fields = Fields::new(ifs);
+        for (part, quoted) in parts {
+            match part {
+                Arg::Var(name) if name == "@" => {
+                    for (index, param) in env.positional().iter().enumerate() {
+                        if index > 0 {
+                            fields.next();
+                        }
+                        match quoted {
+                            true => fields.push(param, true),
+                            false => fields.split(param),
+                        }
+                    }
+                }
+                part => {
+                    let splits = !quoted
+                        && matches!(
+                            part,
+                            Arg::Var(_) | Arg::Param(..) | Arg::Command(_) | Arg::Arith(_)
+                        );
+                    let expanded = Self::expand(env, part, substitute)?;
+                    match splits {
+                        true => fields.split(&expanded),
+                        false => fields.push(&expanded, quoted),
+                    }
+                }
+            }
+        }
+        let mut expanded = Vec::new();
+        for field in fields.finish() {
+            expanded.extend(Self::expand_pathname(env, field)?);
+        }
+        Ok(expanded)
+    }
+
+    /// Replaces a field with a glob pattern by the sorted paths it matches.
+    ///
+    /// Without matches the field is kept as it is, unless the `nullglob` option
+    /// removes it or the `failglob` option makes it an error. The `noglob` option
+    /// disables pathname expansion.
+    fn expand_pathname(env: &Environment, field: Field) -> Result<Vec<String>, ParseError> {
+        if !field.glob || env.option("noglob") {
+            return Ok(vec![field.text]);
+        }
+        let matches = pattern::expand_paths(&field.pattern, &env.current_dir());
+        if !matches.is_empty() {
+            return Ok(matches);
+        }
+        if env.option("failglob") {
+            return Err(ParseError::from(format!("no match: {}", field.text)));
+        }
+        match env.option("nullglob") {
+            true => Ok(Vec::new()),
+            false => Ok(vec![field.text]),
+        }
+    }
+
+    fn expand_word(
+        env: &mut Environment,
+        word: Option<CompoundArg>,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<String, ParseError> {
+        word.into_iter()
+            .flat_map(|word| word.inner)
+            .map(|part| Self::expand(env, part, substitute))
+            .collect()
+    }
+
+    /// Expands a word into a pattern, in which only the unquoted parts can match
+    /// more than themselves.
+    fn expand_pattern(
+        env: &mut Environment,
+        word: Option<CompoundArg>,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<glob::Pattern, ParseError> {
+        let mut pattern = String::new();
+        for part in word.into_iter().flat_map(|word| word.inner) {
+            let quoted = matches!(
+                part,
+                Arg::String(StringArg::SingleQuoted(_) | StringArg::DoubleQuoted(_))
+            );
+            let expanded = Self::expand(env, part, substitute)?;
+            match quoted {
+                true => pattern += &glob::Pattern::escape(&expanded),
+                false => pattern += &expanded,
+            }
+        }
+        glob::Pattern::new(&pattern).map_err(|err| format!("{pattern}: {}", err.msg).into())
+    }
+
+    /// Expands `${name...}`. Operators with a colon treat empty values as unset.
+    fn expand_param(
+        env: &mut Environment,
+        name: String,
+        op: ParamOp,
+        substitute: &mut dyn FnMut(CommandList) -> Result<(String, i32), ParseError>,
+    ) -> Result<String, ParseError> {
+        let value = env.lookup(&name);
+        let is_set = |colon: bool| value.as_ref().is_some_and(|v| !(colon && v.is_empty()));
+        let expanded = match op {
+            ParamOp::Default(colon, word) if !is_set(colon) => {
+                Self::expand_word(env, word, substitute)?
+            }
+            ParamOp::Assign(colon, word) if !is_set(colon) => {
+                let word = Self::expand_word(env, word, substitute)?;
+                env.set(&name, word.clone());
+                word
+            }
+            ParamOp::Error(colon, word) if !is_set(colon) => {
+                let message = match word {
+                    Some(word) => Self::expand_word(env, Some(word), substitute)?,
+                    None => String::from("parameter null or not set"),
+                };
+                return Err(ParseError::fatal(format!("{name}: {message}")));
+            }
+            ParamOp::Alternative(colon, word) => match is_set(colon) {
+                true => Self::expand_word(env, word, substitute)?,
+                false => String::new(),
+            },
+            ParamOp::Default(..) | ParamOp::Assign(..) | ParamOp::Error(..) => {
+                value.unwrap_or_default()
+            }
+            ParamOp::Len if name == "@" || name == "*" => env.positional().len().to_string(),
+            ParamOp::Len => value.unwrap_or_default().chars().count().to_string(),
+            ParamOp::RemovePrefix(longest, word) => {
+                let pattern = Self::expand_pattern(env, word, substitute)?;
+                let value = value.unwrap_or_default();
+                pattern::remove_prefix(&value, &pattern, longest).to_string()
+            }
+            ParamOp::RemoveSuffix(longest, word) => {
+                let pattern = Self::expand_pattern(env, word, substitute)?;
+                let value = value.unwrap_or_default();
+                pattern::remove_suffix(&value, &pattern, longest).to_string()
+            }
+            ParamOp::Replace(anchor, word, replacement) => {
+                let pattern = Self::expand_pattern(env, Some(word), substitute)?;
+                let replacement = Self::expand_word(env, Some(replacement), substitute)?;
+                let value = value.unwrap_or_default();
+                pattern::replace(&value, &pattern, &replacement, anchor)
+            }
+        };
+        Ok(expanded)
+    }
+
+    fn compile_redirect(
+        redirect: ast::Redirect<CompoundArg>,
+        mut arg_to_str: impl FnMut(CompoundArg) -> Result<String, ParseError>,
+    ) -> Result<Redirect, ParseError> {
+        let redirect = match redirect {
+            ast::Redirect::Read(fd, path) => Redirect::Read(fd.unwrap_or(0), arg_to_str(path)?),
+            ast::Redirect::Write(fd, path) | ast::Redirect::Clobber(fd, path) => {
+                Redirect::Write(fd.unwrap_or(1), arg_to_str(path)?)
+            }
+            ast::Redirect::Append(fd, path) => Redirect::Append(fd.unwrap_or(1), arg_to_str(path)?),
+            ast::Redirect::ReadWrite(fd, path) => {
+                Redirect::ReadWrite(fd.unwrap_or(0), arg_to_str(path)?)
+            }
+            ast::Redirect::DupRead(fd, target) => {
+                Self::compile_dup(fd.unwrap_or(0), arg_to_str(target)?)?
+            }
+            ast::Redirect::DupWrite(fd, target) => {
+                Self::compile_dup(fd.unwrap_or(1), arg_to_str(target)?)?
+            }
+            ast::Redirect::Heredoc(..) => return Err("Here-documents are not supported.".into()),
+        };
+        Ok(redirect)
+    }
+
+    fn compile_dup(fd: u16, target: String) -> Result<Redirect, ParseError> {
+        if target == "-" {
+            return Ok(Redirect::Close(fd));
+        }
+        target
+            .parse::<u16>()
+            .map(|target| Redirect::Dup(fd, target))
+            .map_err(|_| format!("{target}: ambiguous redirect").into())
+    }
 }
@@ -1,18 +1,383 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+};
 
+use super::ShellCommandInterm;
+
+/// A shell variable, exported variables are passed to child processes.
+#[derive(Debug, Clone, PartialEq)]
+struct Variable {
+    value: String,
+    exported: bool,
+}
+
+/// Variables replaced by [`Environment::export_temporarily`], with their previous state.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SavedVars(Vec<(String, Option<Variable>)>);
+
+/// Shell variables together with the special parameters of the shell.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Environment {
+    vars: HashMap<String, Variable>,
+    /// `$0`, the name of the shell or of the script it runs.
+    shell_name: String,
+    /// `$1`, `$2` and so on.
+    positional: Vec<String>,
+    /// `$?`, the exit status of the last pipeline.
+    status: i32,
+    /// `$!`, the process id of the last background job.
+    last_background: Option<u32>,
+    /// Shell options enabled with `set -o`.
+    options: BTreeSet<String>,
+    /// Whether the shell reads commands from a user, so that errors which end
+    /// a script do not end it.
+    interactive: bool,
+    /// Number of loops that are running.
+    loop_depth: usize,
+    /// A `break`, `continue`, `return` or exit that has not been handled yet.
+    control: Option<Control>,
+    /// Aliases by name, with their values.
+    aliases: BTreeMap<String, String>,
+    /// Shell functions by name, with their bodies.
+    functions: HashMap<String, ShellCommandInterm>,
+    /// State of the callers of the running functions, innermost last.
+    frames: Vec<Frame>,
+    /// The working directory of the shell, `PWD` is only a copy of it. Unset
+    /// until the shell starts or `cd` changes it.
+    cwd: Option<PathBuf>,
+}
+
+/// Leaves the given number of enclosing loops, continues with the next
+/// iteration of the loop that many levels up, returns from a function, or
+/// leaves the shell or subshell with an exit status.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    Break(usize),
+    Continue(usize),
+    Return,
+    Exit(i32),
+}
+
+/// State of the caller of a function, restored when the function returns.
 #[derive(Debug, Clone, PartialEq)]
-pub struct Environment(HashMap<String, String>);
+struct Frame {
+    positional: Vec<String>,
+    loop_depth: usize,
+    /// Variables made local by the function, with their values outside of it.
+    locals: SavedVars,
+}
 
 impl Environment {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
+    }
+
+    /// Creates an environment with every variable of the shell process exported.
+    /// `PWD` is reset to the working directory of the process if it refers to
+    /// another directory, and becomes the working directory of the shell.
+    pub fn from_process() -> Self {
+        let mut env = Self::new();
+        for (key, value) in std::env::vars() {
+            env.export(&key, Some(value));
+        }
+        if let Ok(cwd) = std::env::current_dir() {
+            let pwd = std::fs::canonicalize(env.get("PWD")).ok();
+            if pwd.as_ref() != std::fs::canonicalize(&cwd).ok().as_ref() {
+                env.export("PWD", Some(cwd.to_string_lossy().into_owned()));
+            }
+            env.cwd = Some(PathBuf::from(env.get("PWD")));
+        }
+        env
     }
 
+    /// Whether `name` can be used as a variable name.
+    pub fn is_valid_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Quotes a value so that it can be used as a double-quoted shell word.
+    pub fn quote(value: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in value.chars() {
+            if matches!(c, '"' | '\\' | '$' | '`') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    /// The value of a variable or a special parameter, or `None` if it is unset.
+    pub fn lookup(&self, key: &str) -> Option<String> {
+        let value = match key {
+            "?" => self.status.to_string(),
+            "$" => std::process::id().to_string(),
+            "!" => self.last_background?.to_string(),
+            "#" => self.positional.len().to_string(),
+            "@" | "*" => self.positional.join(" "),
+            "0" => self.shell_name.clone(),
+            _ => match key.parse::<usize>() {
+                Ok(index) => self.positional.get(index.checked_sub(1)?)?.clone(),
+                Err(_) => self.vars.get(key)?.value.clone(),
+            },
+        };
+        Some(value)
+    }
+
+    /// The value of a variable or a special parameter, empty if it is unset.
     pub fn get(&self, key: &str) -> String {
-        self.0.get(key).unwrap_or(&String::from("")).clone()
+        self.lookup(key).unwrap_or_default()
+    }
+
+    /// Every variable with its value, sorted by name.
+    pub fn variables(&self) -> Vec<(&str, &str)> {
+        let mut vars: Vec<_> = self
+            .vars
+            .iter()
+            .map(|(key, var)| (key.as_str(), var.value.as_str()))
+            .collect();
+        vars.sort();
+        vars
+    }
+
+    pub fn set_shell_name(&mut self, name: String) {
+        self.shell_name = name;
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    pub fn set_positional(&mut self, positional: Vec<String>) {
+        self.positional = positional;
+    }
+
+    /// Removes the first `n` positional parameters, or returns `false` if
+    /// there are fewer of them.
+    pub fn shift(&mut self, n: usize) -> bool {
+        if n > self.positional.len() {
+            return false;
+        }
+        self.positional.drain(..n);
+        true
+    }
+
+    pub fn status(&self) -> i32 {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+
+    pub fn loop_depth(&self) -> usize {
+        self.loop_depth
+    }
+
+    pub fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+    }
+
+    pub fn leave_loop(&mut self) {
+        self.loop_depth = self.loop_depth.saturating_sub(1);
+    }
+
+    /// The pending `break`, `continue`, `return` or exit, which skips the rest of every list
+    /// until a loop takes it.
+    pub fn control(&self) -> Option<Control> {
+        self.control
+    }
+
+    pub fn set_control(&mut self, control: Option<Control>) {
+        self.control = control;
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    /// Every alias with its value, sorted by name.
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn set_alias(&mut self, name: &str, value: &str) {
+        self.aliases.insert(String::from(name), String::from(value));
+    }
+
+    /// Removes an alias, or returns `false` if there is none with the name.
+    pub fn unset_alias(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some()
+    }
+
+    pub fn function(&self, name: &str) -> Option<&ShellCommandInterm> {
+        self.functions.get(name)
+    }
+
+    /// Names of the shell functions.
+    pub fn function_names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+
+    pub fn define_function(&mut self, name: String, body: ShellCommandInterm) {
+        self.functions.insert(name, body);
+    }
+
+    /// Starts a function call with `args` as positional parameters and
+    /// without enclosing loops.
+    pub fn enter_function(&mut self, args: Vec<String>) {
+        self.frames.push(Frame {
+            positional: std::mem::replace(&mut self.positional, args),
+            loop_depth: std::mem::take(&mut self.loop_depth),
+            locals: SavedVars::default(),
+        });
+    }
+
+    /// Ends the innermost function call, restoring the positional parameters
+    /// and the variables it made local.
+    pub fn leave_function(&mut self) {
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        self.positional = frame.positional;
+        self.loop_depth = frame.loop_depth;
+        self.restore(frame.locals);
+    }
+
+    pub fn in_function(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    /// Makes a variable local to the running function. It starts out unset
+    /// unless it already is local. Returns `false` outside of functions.
+    pub fn make_local(&mut self, key: &str) -> bool {
+        let Some(frame) = self.frames.last_mut() else {
+            return false;
+        };
+        if !frame.locals.0.iter().any(|(name, _)| name == key) {
+            frame
+                .locals
+                .0
+                .push((String::from(key), self.vars.remove(key)));
+        }
+        true
+    }
+
+    pub fn set_last_background(&mut self, pid: u32) {
+        self.last_background = Some(pid);
+    }
+
+    /// The working directory of the shell, or the one of the process until it
+    /// is set. Assigning to `PWD` does not change it.
+    pub fn current_dir(&self) -> PathBuf {
+        match &self.cwd {
+            Some(cwd) => cwd.clone(),
+            None => std::env::current_dir().unwrap_or_default(),
+        }
     }
 
+    /// Changes the working directory of the shell and copies it to `PWD`.
+    pub fn set_current_dir(&mut self, dir: PathBuf) {
+        self.set("PWD", dir.to_string_lossy().into_owned());
+        self.cwd = Some(dir);
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    pub fn set_interactive(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
+
+    pub fn option(&self, name: &str) -> bool {
+        self.options.contains(name)
+    }
+
+    pub fn set_option(&mut self, name: &str, enabled: bool) {
+        match enabled {
+            true => self.options.insert(String::from(name)),
+            false => self.options.remove(name),
+        };
+    }
+
+    /// Sets the value of a variable, keeping it exported if it already was.
     pub fn set(&mut self, key: &str, value: String) {
-        self.0.insert(String::from(key), value);
+        match self.vars.get_mut(key) {
+            Some(var) => var.value = value,
+            None => {
+                self.vars.insert(
+                    String::from(key),
+                    Variable {
+                        value,
+                        exported: false,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Marks a variable as exported, setting its value if one is given.
+    pub fn export(&mut self, key: &str, value: Option<String>) {
+        let var = self.vars.entry(String::from(key)).or_insert(Variable {
+            value: String::new(),
+            exported: true,
+        });
+        var.exported = true;
+        if let Some(value) = value {
+            var.value = value;
+        }
+    }
+
+    /// Keeps a variable, but no longer passes it to child processes.
+    pub fn unexport(&mut self, key: &str) {
+        if let Some(var) = self.vars.get_mut(key) {
+            var.exported = false;
+        }
+    }
+
+    pub fn unset(&mut self, key: &str) {
+        self.vars.remove(key);
+    }
+
+    /// The variables passed to child processes.
+    pub fn exported(&self) -> HashMap<String, String> {
+        self.vars
+            .iter()
+            .filter(|(_, var)| var.exported)
+            .map(|(key, var)| (key.clone(), var.value.clone()))
+            .collect()
+    }
+
+    /// Exports the values of `envs` that differ from the exported variables,
+    /// until they are restored with [`Environment::restore`].
+    pub fn export_temporarily(&mut self, envs: &HashMap<String, String>) -> SavedVars {
+        let mut saved = Vec::new();
+        for (key, value) in envs {
+            let previous = self.vars.get(key);
+            if previous.is_some_and(|var| var.exported && &var.value == value) {
+                continue;
+            }
+            saved.push((key.clone(), previous.cloned()));
+            self.export(key, Some(value.clone()));
+        }
+        SavedVars(saved)
+    }
+
+    pub fn restore(&mut self, saved: SavedVars) {
+        for (key, previous) in saved.0 {
+            match previous {
+                Some(var) => self.vars.insert(key, var),
+                None => self.vars.remove(&key),
+            };
+        }
     }
 }
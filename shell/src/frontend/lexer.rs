@@ -0,0 +1,189 @@
+use std::{collections::VecDeque, iter::Peekable};
+
+use conch_parser::token::Token;
+
+/// Marks a bash pattern substitution, see [`TokenRewriter`].
+pub const REPLACE_MARKER: char = '\0';
+
+/// Token stream adapter for syntax that conch-parser does not know about.
+///
+/// It rewrites bash's `&>word` and `&>>word` into the equivalent POSIX
+/// `>word 2>&1` and `>>word 2>&1`.
+///
+/// Bash's `${name/pattern/string}` has no POSIX equivalent, so it is passed
+/// to the parser as `${name#\0/pattern\0string}`, with the operator
+/// (`/`, `//`, `/#` or `/%`) after the first [`REPLACE_MARKER`] and the
+/// second one separating the pattern from the replacement.
+pub struct TokenRewriter<I: Iterator<Item = Token>> {
+    inner: Peekable<I>,
+    pending: VecDeque<Token>,
+}
+
+impl<I: Iterator<Item = Token>> TokenRewriter<I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner: inner.peekable(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Moves the target word of a redirect into `pending`, honoring quotes,
+    /// escapes and `$(...)` that may contain word delimiters.
+    fn take_redirect_word(&mut self) {
+        while let Some(token @ Token::Whitespace(_)) = self.inner.peek() {
+            self.pending.push_back(token.clone());
+            self.inner.next();
+        }
+
+        let mut single_quoted = false;
+        let mut double_quoted = false;
+        let mut depth = 0usize;
+        while let Some(token) = self.inner.peek() {
+            let quoted = single_quoted || double_quoted;
+            if !quoted && depth == 0 && token.is_word_delimiter() {
+                break;
+            }
+
+            let token = self.inner.next().unwrap();
+            match token {
+                Token::SingleQuote if !double_quoted => single_quoted = !single_quoted,
+                Token::DoubleQuote if !single_quoted => double_quoted = !double_quoted,
+                Token::Backslash if !single_quoted => {
+                    self.pending.push_back(token);
+                    if let Some(escaped) = self.inner.next() {
+                        self.pending.push_back(escaped);
+                    }
+                    continue;
+                }
+                Token::Dollar if !single_quoted => {
+                    if self.inner.peek() == Some(&Token::ParenOpen) {
+                        depth += 1;
+                        self.pending.push_back(token);
+                        self.pending.push_back(self.inner.next().unwrap());
+                        continue;
+                    }
+                }
+                Token::ParenOpen if !quoted && depth > 0 => depth += 1,
+                Token::ParenClose if !quoted && depth > 0 => depth -= 1,
+                _ => {}
+            }
+            self.pending.push_back(token);
+        }
+    }
+
+    /// Rewrites `${name/pattern/string}` after its `$` was consumed, leaving the
+    /// tokens of other parameter expansions untouched in `pending`.
+    fn take_parameter(&mut self) {
+        self.pending.push_back(self.inner.next().unwrap());
+        let Some(Token::Name(_) | Token::Literal(_)) = self.inner.peek() else {
+            return;
+        };
+        self.pending.push_back(self.inner.next().unwrap());
+        if self.inner.peek() != Some(&Token::Slash) {
+            return;
+        }
+        self.inner.next();
+
+        let mut operator = String::from("/");
+        if let Some(token @ (Token::Slash | Token::Pound | Token::Percent)) = self.inner.peek() {
+            operator += token.as_str();
+            self.inner.next();
+        }
+
+        let mut body = Vec::new();
+        let mut single_quoted = false;
+        let mut double_quoted = false;
+        let mut separated = false;
+        let mut depth = 0usize;
+        while let Some(token) = self.inner.next() {
+            let quoted = single_quoted || double_quoted;
+            match token {
+                Token::SingleQuote if !double_quoted => single_quoted = !single_quoted,
+                Token::DoubleQuote if !single_quoted => double_quoted = !double_quoted,
+                Token::Backslash if !single_quoted => {
+                    body.push(token);
+                    body.extend(self.inner.next());
+                    continue;
+                }
+                Token::CurlyOpen if !quoted => depth += 1,
+                Token::CurlyClose if !quoted && depth == 0 => break,
+                Token::CurlyClose if !quoted => depth -= 1,
+                Token::Slash if !quoted && depth == 0 && !separated => {
+                    separated = true;
+                    body.push(Token::Literal(REPLACE_MARKER.to_string()));
+                    continue;
+                }
+                _ => {}
+            }
+            body.push(token);
+        }
+
+        self.pending.push_back(Token::Pound);
+        self.pending
+            .push_back(Token::Literal(format!("{REPLACE_MARKER}{operator}")));
+        self.pending.extend(TokenRewriter::new(body.into_iter()));
+        self.pending.push_back(Token::CurlyClose);
+    }
+}
+
+impl<I: Iterator<Item = Token>> Iterator for TokenRewriter<I> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(token);
+        }
+
+        let token = self.inner.next()?;
+        if token == Token::Dollar && self.inner.peek() == Some(&Token::CurlyOpen) {
+            self.take_parameter();
+            return Some(token);
+        }
+        if token != Token::Amp || !matches!(self.inner.peek(), Some(Token::Great | Token::DGreat)) {
+            return Some(token);
+        }
+
+        let redirect = self.inner.next().unwrap();
+        self.take_redirect_word();
+        self.pending.extend([
+            Token::Whitespace(String::from(" ")),
+            Token::Literal(String::from("2")),
+            Token::GreatAnd,
+            Token::Literal(String::from("1")),
+        ]);
+        Some(redirect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use conch_parser::lexer::Lexer;
+
+    use super::*;
+
+    fn rewrite(input: &str) -> String {
+        TokenRewriter::new(Lexer::new(input.chars()))
+            .map(|token| token.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_rewrite_all_output_redirects() {
+        assert_eq!(rewrite("cmd &> out"), "cmd > out 2>&1");
+        assert_eq!(rewrite("cmd &>>out; next"), "cmd >>out 2>&1; next");
+        assert_eq!(rewrite(r#"cmd &>"a b"\ c|x"#), r#"cmd >"a b"\ c 2>&1|x"#);
+        assert_eq!(rewrite("cmd &>$(echo a b) &"), "cmd >$(echo a b) 2>&1 &");
+        assert_eq!(rewrite("cmd & > out"), "cmd & > out");
+    }
+
+    #[test]
+    fn test_rewrite_pattern_substitution() {
+        assert_eq!(rewrite("${a/b/c} ${a}"), "${a#\0/b\0c} ${a}");
+        assert_eq!(rewrite("${a//b}${a/#b/}"), "${a#\0//b}${a#\0/#b\0}");
+        assert_eq!(
+            rewrite(r#"${a/'/'/"}"} ${a%/b}"#),
+            "${a#\0/'/'\0\"}\"} ${a%/b}"
+        );
+        assert_eq!(rewrite("${a/${b/c/d}/${e}}"), "${a#\0/${b#\0/c\0d}\0${e}}");
+    }
+}
@@ -1,12 +1,20 @@
-use crate::ir::PipeCommand;
+use crate::ir::{AndOrList, CommandList, CompoundCommand, ListOperator, PipeCommand, Pipeline};
 use conch_parser::ast;
 use conch_parser::lexer::Lexer;
-use conch_parser::parse::DefaultParser;
-use env::Environment;
+use conch_parser::parse::{self, DefaultParser};
+pub use env::{Control, Environment};
+use lexer::{TokenRewriter, REPLACE_MARKER};
 use std::fmt::{Debug, Display};
+use std::path::PathBuf;
 
+mod alias;
+pub mod arith;
+mod brace;
 pub mod compiler;
 mod env;
+mod lexer;
+pub mod pattern;
+mod tilde;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum StringArg {
@@ -16,54 +24,204 @@ pub enum StringArg {
     Simple(String),
 }
 
-impl StringArg {
-    fn inner(&self, env: &Environment) -> String {
-        match self {
-            StringArg::DoubleQuoted(inner) => {
-                let strings_list: Vec<String> = inner
-                    .iter()
-                    .map(|a| match a {
-                        Arg::String(string_arg) => match string_arg {
-                            StringArg::DoubleQuoted(_) => {
-                                panic!("Recursive DoubleQuoted string met.")
-                            }
-                            StringArg::SingleQuoted(inner) | StringArg::Simple(inner) => {
-                                inner.clone()
-                            }
-                        },
-                        Arg::Var(name) => env.get(name),
-                        Arg::Number(n) => n.to_string(),
-                    })
-                    .collect();
-                strings_list.join("")
-            }
-            StringArg::SingleQuoted(inner) | StringArg::Simple(inner) => inner.clone(),
-        }
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub enum Arg {
     String(StringArg),
     Var(String),
     Number(f64),
+    /// Command substitution, `$(...)` or backticks.
+    Command(CommandList),
+    /// Arithmetic expansion, `$((...))`.
+    Arith(Option<ast::DefaultArithmetic>),
+    /// Parameter expansion with an operator, `${name...}`.
+    Param(String, ParamOp),
+}
+
+/// Operator of a parameter expansion. The flag of the first four is set when
+/// the operator is written with a colon, so that empty values count as unset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamOp {
+    /// `${name:-word}`, the word if the parameter is unset.
+    Default(bool, Option<CompoundArg>),
+    /// `${name:=word}`, assigns the word if the parameter is unset.
+    Assign(bool, Option<CompoundArg>),
+    /// `${name:?word}`, fails with the word as a message if the parameter is unset.
+    Error(bool, Option<CompoundArg>),
+    /// `${name:+word}`, the word if the parameter is set.
+    Alternative(bool, Option<CompoundArg>),
+    /// `${#name}`, the length of the value in characters.
+    Len,
+    /// `${name#pattern}`, or `${name##pattern}` to remove the longest match.
+    RemovePrefix(bool, Option<CompoundArg>),
+    /// `${name%pattern}`, or `${name%%pattern}` to remove the longest match.
+    RemoveSuffix(bool, Option<CompoundArg>),
+    /// `${name/pattern/string}`.
+    Replace(pattern::Anchor, CompoundArg, CompoundArg),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ShellCommandInterm {
     Execute {
+        /// Prefix assignments, visible only in the environment of the command.
+        assignments: Vec<(String, Option<CompoundArg>)>,
         name: CompoundArg,
         args: Vec<CompoundArg>,
+        redirects: Vec<ast::Redirect<CompoundArg>>,
     },
     Assign {
         name: String,
         value: Option<CompoundArg>,
     },
+    /// A compound command with the redirections of all its commands.
+    Compound {
+        command: Box<CompoundCommand>,
+        redirects: Vec<ast::Redirect<CompoundArg>>,
+    },
+    /// `name() body`, defines a function with a compound command as body.
+    Function {
+        name: String,
+        body: Box<ShellCommandInterm>,
+    },
+}
+
+fn write_redirects(
+    f: &mut std::fmt::Formatter<'_>,
+    redirects: &[ast::Redirect<CompoundArg>],
+) -> std::fmt::Result {
+    for redirect in redirects {
+        let (fd, operator, word) = match redirect {
+            ast::Redirect::Read(fd, word) => (fd, "<", word),
+            ast::Redirect::Write(fd, word) => (fd, ">", word),
+            ast::Redirect::ReadWrite(fd, word) => (fd, "<>", word),
+            ast::Redirect::Append(fd, word) => (fd, ">>", word),
+            ast::Redirect::Clobber(fd, word) => (fd, ">|", word),
+            ast::Redirect::DupRead(fd, word) => (fd, "<&", word),
+            ast::Redirect::DupWrite(fd, word) => (fd, ">&", word),
+            ast::Redirect::Heredoc(fd, word) => (fd, "<<", word),
+        };
+        write!(f, " ")?;
+        if let Some(fd) = fd {
+            write!(f, "{fd}")?;
+        }
+        write!(f, "{operator}{word}")?;
+    }
+    Ok(())
+}
+
+impl Display for Arg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arg::String(StringArg::Simple(inner)) => write!(f, "{inner}"),
+            Arg::String(StringArg::SingleQuoted(inner)) => write!(f, "'{inner}'"),
+            Arg::String(StringArg::DoubleQuoted(inner)) => {
+                write!(f, "\"")?;
+                for arg in inner {
+                    write!(f, "{arg}")?;
+                }
+                write!(f, "\"")
+            }
+            Arg::Var(name) if name.len() > 1 && name.parse::<usize>().is_ok() => {
+                write!(f, "${{{name}}}")
+            }
+            Arg::Var(name) => write!(f, "${name}"),
+            Arg::Number(n) => write!(f, "{n}"),
+            Arg::Command(list) => write!(f, "$({list})"),
+            Arg::Arith(None) => write!(f, "$(())"),
+            Arg::Arith(Some(expr)) => write!(f, "$(({}))", arith::Expr(expr)),
+            Arg::Param(name, op) => {
+                let word = |colon: &bool, operator, word: &Option<CompoundArg>| {
+                    let colon = if *colon { ":" } else { "" };
+                    let word = word.as_ref().map(ToString::to_string).unwrap_or_default();
+                    format!("{colon}{operator}{word}")
+                };
+                let op = match op {
+                    ParamOp::Default(colon, w) => word(colon, "-", w),
+                    ParamOp::Assign(colon, w) => word(colon, "=", w),
+                    ParamOp::Error(colon, w) => word(colon, "?", w),
+                    ParamOp::Alternative(colon, w) => word(colon, "+", w),
+                    ParamOp::Len => return write!(f, "${{#{name}}}"),
+                    ParamOp::RemovePrefix(true, w) => word(&false, "##", w),
+                    ParamOp::RemovePrefix(false, w) => word(&false, "#", w),
+                    ParamOp::RemoveSuffix(true, w) => word(&false, "%%", w),
+                    ParamOp::RemoveSuffix(false, w) => word(&false, "%", w),
+                    ParamOp::Replace(anchor, pattern, replacement) => {
+                        format!("{anchor}{pattern}/{replacement}")
+                    }
+                };
+                write!(f, "${{{name}{op}}}")
+            }
+        }
+    }
+}
+
+impl Display for ShellCommandInterm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShellCommandInterm::Execute {
+                assignments,
+                name,
+                args,
+                redirects,
+            } => {
+                for (assignment_name, value) in assignments {
+                    write!(f, "{assignment_name}=")?;
+                    if let Some(value) = value {
+                        write!(f, "{value}")?;
+                    }
+                    write!(f, " ")?;
+                }
+                write!(f, "{name}")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+                write_redirects(f, redirects)
+            }
+            ShellCommandInterm::Assign { name, value } => {
+                write!(f, "{name}=")?;
+                if let Some(value) = value {
+                    write!(f, "{value}")?;
+                }
+                Ok(())
+            }
+            ShellCommandInterm::Compound { command, redirects } => {
+                write!(f, "{command}")?;
+                write_redirects(f, redirects)
+            }
+            ShellCommandInterm::Function { name, body } => write!(f, "{name}() {body}"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParseError {
     message: String,
+    /// Whether the input ended in the middle of a command, which may continue
+    /// on the next line.
+    incomplete: bool,
+    /// Whether the error ends a non-interactive shell, as `${name?word}` does.
+    fatal: bool,
+}
+
+impl ParseError {
+    fn incomplete(message: String) -> Self {
+        ParseError {
+            message,
+            incomplete: true,
+            fatal: false,
+        }
+    }
+
+    fn fatal(message: String) -> Self {
+        ParseError {
+            message,
+            incomplete: false,
+            fatal: true,
+        }
+    }
+
+    pub fn is_incomplete(&self) -> bool {
+        self.incomplete
+    }
 }
 
 impl Display for ParseError {
@@ -72,17 +230,21 @@ impl Display for ParseError {
     }
 }
 
+impl std::error::Error for ParseError {}
+
 impl From<&str> for ParseError {
     fn from(value: &str) -> Self {
-        ParseError {
-            message: String::from(value),
-        }
+        ParseError::from(String::from(value))
     }
 }
 
 impl From<String> for ParseError {
     fn from(value: String) -> Self {
-        ParseError { message: value }
+        ParseError {
+            message: value,
+            incomplete: false,
+            fatal: false,
+        }
     }
 }
 
@@ -99,18 +261,123 @@ impl CompoundArg {
     }
 }
 
-fn parse_top_level_word<T: Debug + Display>(
-    word: ast::TopLevelWord<T>,
-) -> Result<CompoundArg, ParseError> {
+impl Display for CompoundArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for arg in &self.inner {
+            write!(f, "{arg}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Names a parameter the way it is looked up in the [`Environment`].
+fn parse_parameter(parameter: ast::DefaultParameter) -> Result<String, ParseError> {
+    let name = match parameter {
+        ast::Parameter::Var(var) => var,
+        ast::Parameter::Positional(n) => n.to_string(),
+        ast::Parameter::At => String::from("@"),
+        ast::Parameter::Star => String::from("*"),
+        ast::Parameter::Pound => String::from("#"),
+        ast::Parameter::Question => String::from("?"),
+        ast::Parameter::Dollar => String::from("$"),
+        ast::Parameter::Bang => String::from("!"),
+        ast::Parameter::Dash => return Err("The $- parameter is not supported.".into()),
+    };
+    Ok(name)
+}
+
+/// Parses a parameter expansion with an operator, `${name...}`.
+fn parse_parameter_substitution(
+    subst: ast::DefaultParameterSubstitution,
+) -> Result<Arg, ParseError> {
+    use ast::ParameterSubstitution as Subst;
+
+    let word = |word: Option<ast::TopLevelWord<String>>| word.map(parse_top_level_word).transpose();
+    let (parameter, op) = match subst {
+        Subst::Command(commands) => return Ok(Arg::Command(parse_command_list(commands)?)),
+        Subst::Arith(expr) => return Ok(Arg::Arith(expr)),
+        Subst::Len(parameter) => (parameter, ParamOp::Len),
+        Subst::Default(colon, parameter, w) => (parameter, ParamOp::Default(colon, word(w)?)),
+        Subst::Assign(colon, parameter, w) => (parameter, ParamOp::Assign(colon, word(w)?)),
+        Subst::Error(colon, parameter, w) => (parameter, ParamOp::Error(colon, word(w)?)),
+        Subst::Alternative(colon, parameter, w) => {
+            (parameter, ParamOp::Alternative(colon, word(w)?))
+        }
+        Subst::RemoveSmallestSuffix(parameter, w) => {
+            (parameter, ParamOp::RemoveSuffix(false, word(w)?))
+        }
+        Subst::RemoveLargestSuffix(parameter, w) => {
+            (parameter, ParamOp::RemoveSuffix(true, word(w)?))
+        }
+        Subst::RemoveSmallestPrefix(parameter, w) => {
+            let w = word(w)?;
+            let op = match w.clone().and_then(parse_replace) {
+                Some((anchor, pattern, replacement)) => {
+                    ParamOp::Replace(anchor, pattern, replacement)
+                }
+                None => ParamOp::RemovePrefix(false, w),
+            };
+            (parameter, op)
+        }
+        Subst::RemoveLargestPrefix(parameter, w) => {
+            (parameter, ParamOp::RemovePrefix(true, word(w)?))
+        }
+    };
+    Ok(Arg::Param(parse_parameter(parameter)?, op))
+}
+
+/// Splits the word of `${name/pattern/string}`, which reaches the parser as
+/// `${name#\0/pattern\0string}`, see [`TokenRewriter`].
+fn parse_replace(word: CompoundArg) -> Option<(pattern::Anchor, CompoundArg, CompoundArg)> {
+    let mut args = word.inner.into_iter();
+    let Some(Arg::String(StringArg::Simple(first))) = args.next() else {
+        return None;
+    };
+    let operator = first.strip_prefix(REPLACE_MARKER)?.strip_prefix('/')?;
+    let (anchor, rest) = match operator.chars().next() {
+        Some('/') => (pattern::Anchor::All, &operator[1..]),
+        Some('#') => (pattern::Anchor::Start, &operator[1..]),
+        Some('%') => (pattern::Anchor::End, &operator[1..]),
+        _ => (pattern::Anchor::First, operator),
+    };
+
+    let mut pattern = Vec::new();
+    let mut replacement = Vec::new();
+    let mut separated = false;
+    let rest = (!rest.is_empty()).then(|| Arg::String(StringArg::Simple(rest.to_string())));
+    for arg in rest.into_iter().chain(args) {
+        match arg {
+            Arg::String(StringArg::Simple(text)) if !separated && text.contains(REPLACE_MARKER) => {
+                let (before, after) = text.split_once(REPLACE_MARKER).unwrap();
+                separated = true;
+                if !before.is_empty() {
+                    pattern.push(Arg::String(StringArg::Simple(before.to_string())));
+                }
+                if !after.is_empty() {
+                    replacement.push(Arg::String(StringArg::Simple(after.to_string())));
+                }
+            }
+            arg if separated => replacement.push(arg),
+            arg => pattern.push(arg),
+        }
+    }
+    Some((
+        anchor,
+        CompoundArg::new(pattern),
+        CompoundArg::new(replacement),
+    ))
+}
+
+fn parse_top_level_word(word: ast::TopLevelWord<String>) -> Result<CompoundArg, ParseError> {
     let words = match word.0 {
         ast::ShellWord::Single(s) => {
             vec![s]
         }
         ast::ShellWord::Concat(s_v) => s_v,
     };
-    let parse_simple_word = |sw| match sw {
+    let parse_simple_word = |sw: ast::DefaultSimpleWord| match sw {
         ast::SimpleWord::Literal(l) => {
-            let l_strint = format!("{l}");
+            let l_strint = l;
             let parsed_number = l_strint.parse::<f64>();
             let arg = if let Ok(parsed_number) = parsed_number {
                 Arg::Number(parsed_number)
@@ -119,13 +386,15 @@ fn parse_top_level_word<T: Debug + Display>(
             };
             Ok(arg)
         }
-        ast::SimpleWord::Param(p) => {
-            let ast::Parameter::Var(var) = p else {
-                return Err("Only var parameters of $var view are supported.".into());
-            };
-            Ok(Arg::Var(format!("{var}")))
-        }
-        _ => Err(ParseError::from(format!("Unsupported token: {sw:?}."))),
+        ast::SimpleWord::Param(p) => Ok(Arg::Var(parse_parameter(p)?)),
+        ast::SimpleWord::Subst(subst) => parse_parameter_substitution(*subst),
+        ast::SimpleWord::Escaped(escaped) => Ok(Arg::String(StringArg::SingleQuoted(escaped))),
+        ast::SimpleWord::Star => Ok(Arg::String(StringArg::Simple(String::from("*")))),
+        ast::SimpleWord::Question => Ok(Arg::String(StringArg::Simple(String::from("?")))),
+        ast::SimpleWord::SquareOpen => Ok(Arg::String(StringArg::Simple(String::from("[")))),
+        ast::SimpleWord::SquareClose => Ok(Arg::String(StringArg::Simple(String::from("]")))),
+        ast::SimpleWord::Colon => Ok(Arg::String(StringArg::Simple(String::from(":")))),
+        ast::SimpleWord::Tilde => Ok(Arg::String(StringArg::Simple(String::from("~")))),
     };
 
     let mut processed_args = Vec::new();
@@ -137,99 +406,239 @@ fn parse_top_level_word<T: Debug + Display>(
                     dq.into_iter().map(parse_simple_word).collect();
                 Arg::String(StringArg::DoubleQuoted(parsed?))
             }
-            ast::Word::SingleQuoted(sq) => Arg::String(StringArg::SingleQuoted(format!("{sq}"))),
+            ast::Word::SingleQuoted(sq) => Arg::String(StringArg::SingleQuoted(sq)),
         };
         processed_args.push(arg)
     }
     Ok(CompoundArg::new(processed_args))
 }
 
-/// Parses the intermediate representation of a shell command from the input string.
+fn parse_redirect(
+    redirect: ast::Redirect<ast::TopLevelWord<String>>,
+) -> Result<ast::Redirect<CompoundArg>, ParseError> {
+    use ast::Redirect;
+
+    let redirect = match redirect {
+        Redirect::Read(fd, word) => Redirect::Read(fd, parse_top_level_word(word)?),
+        Redirect::Write(fd, word) => Redirect::Write(fd, parse_top_level_word(word)?),
+        Redirect::ReadWrite(fd, word) => Redirect::ReadWrite(fd, parse_top_level_word(word)?),
+        Redirect::Append(fd, word) => Redirect::Append(fd, parse_top_level_word(word)?),
+        Redirect::Clobber(fd, word) => Redirect::Clobber(fd, parse_top_level_word(word)?),
+        Redirect::DupRead(fd, word) => Redirect::DupRead(fd, parse_top_level_word(word)?),
+        Redirect::DupWrite(fd, word) => Redirect::DupWrite(fd, parse_top_level_word(word)?),
+        Redirect::Heredoc(..) => return Err("Here-documents are not supported.".into()),
+    };
+    Ok(redirect)
+}
+
+/// Parses the intermediate representation of all statements in the input string.
 ///
-/// It currently supports only simple single-command inputs.
+/// Pipelines stay uncompiled in the resulting list, so that each of them
+/// can be compiled right before it runs.
 ///
 /// # Errors
 ///
-/// This function will return errors for unsupported syntax such as asynchronous commands,
-/// logical operators, functions, and file descriptor redirection. It also returns errors
-/// when parsing fails.
-pub fn parse_intermediate(input: &str) -> Result<Vec<ShellCommandInterm>, ParseError> {
-    let lex = Lexer::new(input.chars());
-    let parser = DefaultParser::new(lex);
-    let parse_res = parser.into_iter().next();
-    let Some(parse_res) = parse_res else {
-        return Err("Unable to parse (possibly empty) input".into());
-    };
+/// This function will return errors for unsupported syntax such as
+/// here-documents. It also returns errors when parsing fails.
+pub fn parse_intermediate(input: &str) -> Result<CommandList, ParseError> {
+    let trailing_backslashes = input
+        .trim_end_matches('\n')
+        .chars()
+        .rev()
+        .take_while(|&c| c == '\\')
+        .count();
+    if trailing_backslashes % 2 == 1 {
+        return Err(ParseError::incomplete(String::from(
+            "unexpected end of input",
+        )));
+    }
+    let lex = TokenRewriter::new(Lexer::new(input.chars()));
+    let commands = DefaultParser::new(lex)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| match err {
+            parse::ParseError::Unmatched(..)
+            | parse::ParseError::IncompleteCmd(..)
+            | parse::ParseError::UnexpectedEOF => ParseError::incomplete(format!("{err}")),
+            err => ParseError::from(format!("{err}")),
+        })?;
+    parse_command_list(commands)
+}
 
-    let Ok(parse_res) = parse_res else {
-        // Fatal error input
-        return Err(format!("{parse_res:?}").into());
-    };
-    let command = parse_res.0;
-    let ast::Command::List(commands_list) = command else {
-        return Err("Async commands with & are unsupported.".into());
-    };
-    // We don't support "||" and "&&" so that `commands_list.second` is empty.
-    let first_command = commands_list.first;
-    let commands_vec = match first_command {
-        ast::ListableCommand::Pipe(_, commands) => commands,
-        ast::ListableCommand::Single(command) => {
-            vec![command]
+fn parse_command_list(
+    commands: Vec<ast::TopLevelCommand<String>>,
+) -> Result<CommandList, ParseError> {
+    let mut items = Vec::new();
+    for command in commands {
+        let (commands_list, background) = match command.0 {
+            ast::Command::List(commands_list) => (commands_list, false),
+            ast::Command::Job(commands_list) => (commands_list, true),
+        };
+        let first = parse_pipeline(commands_list.first)?;
+        let mut rest = Vec::new();
+        for and_or in commands_list.rest {
+            let (operator, command) = match and_or {
+                ast::AndOr::And(command) => (ListOperator::And, command),
+                ast::AndOr::Or(command) => (ListOperator::Or, command),
+            };
+            rest.push((operator, parse_pipeline(command)?));
         }
+        items.push(AndOrList {
+            first,
+            rest,
+            background,
+        });
+    }
+    Ok(CommandList { items })
+}
+
+fn parse_pipeline(command: ast::DefaultListableCommand) -> Result<Pipeline, ParseError> {
+    let (negated, commands_vec) = match command {
+        ast::ListableCommand::Pipe(negated, commands) => (negated, commands),
+        ast::ListableCommand::Single(command) => (false, vec![command]),
     };
     let mut piped_commands = Vec::new();
     for command in commands_vec {
-        let ast::PipeableCommand::Simple(simple_command) = command else {
-            return Err("Functions and fd redirection are not supported.".into());
+        let simple_command = match command {
+            ast::PipeableCommand::Simple(simple_command) => simple_command,
+            ast::PipeableCommand::Compound(compound) => {
+                piped_commands.push(parse_compound(*compound)?);
+                continue;
+            }
+            ast::PipeableCommand::FunctionDef(name, body) => {
+                piped_commands.push(ShellCommandInterm::Function {
+                    name,
+                    body: Box::new(parse_compound((*body).clone())?),
+                });
+                continue;
+            }
         };
 
-        if !simple_command.redirects_or_env_vars.is_empty() {
-            // Case of variable assign.
-            let command_values = simple_command.redirects_or_env_vars;
-            for assign in command_values {
-                let ast::RedirectOrEnvVar::EnvVar(name, value) = assign else {
-                    return Err(
-                        "Expected variable declaration. Redirection is not supported.".into(),
-                    );
-                };
-                let value = value.map(parse_top_level_word);
-                let value = match value {
-                    None => None,
-                    Some(value) => Some(value?),
-                };
-                piped_commands.push(ShellCommandInterm::Assign { name, value });
+        let mut redirects = Vec::new();
+        let mut assignments = Vec::new();
+        for redirect_or_env_var in simple_command.redirects_or_env_vars {
+            match redirect_or_env_var {
+                ast::RedirectOrEnvVar::EnvVar(name, value) => {
+                    let value = value.map(parse_top_level_word).transpose()?;
+                    assignments.push((name, value));
+                }
+                ast::RedirectOrEnvVar::Redirect(redirect) => {
+                    redirects.push(parse_redirect(redirect)?);
+                }
             }
+        }
+        if simple_command.redirects_or_cmd_words.is_empty() && !assignments.is_empty() {
+            // Case of variable assign without a command.
+            piped_commands.extend(
+                assignments
+                    .into_iter()
+                    .map(|(name, value)| ShellCommandInterm::Assign { name, value }),
+            );
             continue;
         }
 
-        let command_values = simple_command.redirects_or_cmd_words;
-
-        let values_parsed: Result<Vec<CompoundArg>, ParseError> = command_values
-            .into_iter()
-            .map(|value| {
-                let ast::RedirectOrCmdWord::CmdWord(toplevel_word) = value else {
-                    return Err(ParseError::from(
-                        "Expected command word token. Redirection is not supported.",
-                    ));
-                };
-                parse_top_level_word(toplevel_word)
-            })
-            .collect();
-        let values_parsed = values_parsed?;
-        let (name, args) = values_parsed
-            .split_first()
-            .expect("expected command with args.");
+        let mut values_parsed = Vec::new();
+        for value in simple_command.redirects_or_cmd_words {
+            match value {
+                ast::RedirectOrCmdWord::CmdWord(toplevel_word) => {
+                    values_parsed.push(parse_top_level_word(toplevel_word)?);
+                }
+                ast::RedirectOrCmdWord::Redirect(redirect) => {
+                    redirects.push(parse_redirect(redirect)?);
+                }
+            }
+        }
+        let Some((name, args)) = values_parsed.split_first() else {
+            return Err("Redirection without a command is not supported.".into());
+        };
         piped_commands.push(ShellCommandInterm::Execute {
+            assignments,
             name: name.clone(),
             args: args.to_vec(),
+            redirects,
         })
     }
 
-    Ok(piped_commands)
+    Ok(Pipeline {
+        negated,
+        commands: piped_commands,
+    })
+}
+
+fn parse_compound(compound: ast::DefaultCompoundCommand) -> Result<ShellCommandInterm, ParseError> {
+    let command = match compound.kind {
+        ast::CompoundCommandKind::Brace(commands) => {
+            CompoundCommand::Brace(parse_command_list(commands)?)
+        }
+        ast::CompoundCommandKind::Subshell(commands) => {
+            CompoundCommand::Subshell(parse_command_list(commands)?)
+        }
+        ast::CompoundCommandKind::While(pair) => CompoundCommand::Loop {
+            until: false,
+            condition: parse_command_list(pair.guard)?,
+            body: parse_command_list(pair.body)?,
+        },
+        ast::CompoundCommandKind::Until(pair) => CompoundCommand::Loop {
+            until: true,
+            condition: parse_command_list(pair.guard)?,
+            body: parse_command_list(pair.body)?,
+        },
+        ast::CompoundCommandKind::If {
+            conditionals,
+            else_branch,
+        } => CompoundCommand::If {
+            conditionals: conditionals
+                .into_iter()
+                .map(|pair| {
+                    Ok((
+                        parse_command_list(pair.guard)?,
+                        parse_command_list(pair.body)?,
+                    ))
+                })
+                .collect::<Result<_, ParseError>>()?,
+            else_branch: else_branch.map(parse_command_list).transpose()?,
+        },
+        ast::CompoundCommandKind::For { var, words, body } => CompoundCommand::For {
+            name: var,
+            words: words
+                .map(|words| words.into_iter().map(parse_top_level_word).collect())
+                .transpose()?,
+            body: parse_command_list(body)?,
+        },
+        ast::CompoundCommandKind::Case { word, arms } => CompoundCommand::Case {
+            word: parse_top_level_word(word)?,
+            arms: arms
+                .into_iter()
+                .map(|arm| {
+                    let patterns = arm
+                        .patterns
+                        .into_iter()
+                        .map(parse_top_level_word)
+                        .collect::<Result<_, ParseError>>()?;
+                    Ok((patterns, parse_command_list(arm.body)?))
+                })
+                .collect::<Result<_, ParseError>>()?,
+        },
+    };
+    let redirects = compound
+        .io
+        .into_iter()
+        .map(parse_redirect)
+        .collect::<Result<_, ParseError>>()?;
+    Ok(ShellCommandInterm::Compound {
+        command: Box::new(command),
+        redirects,
+    })
 }
 
+/// Runs the commands of a command substitution in the given subshell and
+/// returns their standard output and exit status.
+pub type Substitute<'a> =
+    dyn FnMut(CommandList, &mut Frontend) -> Result<(String, i32), ParseError> + 'a;
+
 /// The frontend transforms commands from string representation
 /// to an executable form using compiler
+#[derive(Clone)]
 pub struct Frontend {
     c: compiler::Compiler,
 }
@@ -241,9 +650,91 @@ impl Frontend {
         }
     }
 
-    pub fn parse(&mut self, input: &str) -> Result<PipeCommand, ParseError> {
-        let interm = parse_intermediate(input)?;
-        self.c.compile(interm)
+    /// Parses the input and expands the aliases in it, as defined before any
+    /// of the input runs.
+    pub fn parse(&mut self, input: &str) -> Result<CommandList, ParseError> {
+        let mut list = parse_intermediate(input)?;
+        alias::expand_list(&mut list, &self.c.env)?;
+        Ok(list)
+    }
+
+    pub fn env(&self) -> &Environment {
+        &self.c.env
+    }
+
+    pub fn env_mut(&mut self) -> &mut Environment {
+        &mut self.c.env
+    }
+
+    /// The working directory of the shell, see [`Environment::current_dir`].
+    pub fn current_dir(&self) -> PathBuf {
+        self.c.env.current_dir()
+    }
+
+    /// Expands and compiles a pipeline of the list returned by [`Frontend::parse`].
+    ///
+    /// Command substitutions are run by `substitute` with a copy of the frontend,
+    /// so that they cannot change the state of the shell.
+    pub fn compile(
+        &mut self,
+        pipeline: Pipeline,
+        substitute: &mut Substitute,
+    ) -> Result<PipeCommand, ParseError> {
+        let subshell = self.clone();
+        let result = self.c.compile(pipeline.commands, &mut |list| {
+            substitute(list, &mut subshell.clone())
+        });
+        self.exit_on_fatal(result)
+    }
+
+    /// Expands the words of `for` into fields, like the arguments of a command.
+    pub fn expand_arguments(
+        &mut self,
+        words: Vec<CompoundArg>,
+        substitute: &mut Substitute,
+    ) -> Result<Vec<String>, ParseError> {
+        let subshell = self.clone();
+        let result = self
+            .c
+            .expand_arguments(words, &mut |list| substitute(list, &mut subshell.clone()));
+        self.exit_on_fatal(result)
+    }
+
+    /// Expands the word of `case`.
+    pub fn expand_string(
+        &mut self,
+        word: CompoundArg,
+        substitute: &mut Substitute,
+    ) -> Result<String, ParseError> {
+        let subshell = self.clone();
+        let result = self
+            .c
+            .expand_string(word, &mut |list| substitute(list, &mut subshell.clone()));
+        self.exit_on_fatal(result)
+    }
+
+    /// Expands a pattern of `case`, in which only unquoted parts can match
+    /// more than themselves.
+    pub fn expand_glob(
+        &mut self,
+        word: CompoundArg,
+        substitute: &mut Substitute,
+    ) -> Result<glob::Pattern, ParseError> {
+        let subshell = self.clone();
+        let result = self
+            .c
+            .expand_glob(word, &mut |list| substitute(list, &mut subshell.clone()));
+        self.exit_on_fatal(result)
+    }
+
+    /// Makes a non-interactive shell exit with status 1 after a fatal error.
+    fn exit_on_fatal<T>(&mut self, result: Result<T, ParseError>) -> Result<T, ParseError> {
+        if let Err(ParseError { fatal: true, .. }) = &result {
+            if !self.env().is_interactive() {
+                self.env_mut().set_control(Some(Control::Exit(1)));
+            }
+        }
+        result
     }
 }
 
@@ -254,16 +745,25 @@ mod tests {
     use crate::{
         builtins::{cat::CatCommand, echo::EchoCommand},
         frontend::{env::Environment, Arg, StringArg},
-        ir::CallCommand,
+        ir::{CallCommand, ListOperator, PipeCommand, Redirect},
     };
 
-    use super::{parse_intermediate, Frontend, ShellCommandInterm};
+    use super::{parse_intermediate, Frontend, ParseError, ShellCommandInterm};
+
+    /// Compiles the input, which must be a single pipeline.
+    fn parse_full(front: &mut Frontend, input: &str) -> Result<PipeCommand, ParseError> {
+        let mut items = front.parse(input)?.items;
+        assert_eq!(items.len(), 1, "{input}: expected a single pipeline");
+        front.compile(items.remove(0).first, &mut |list, _| {
+            Ok((format!("<{list}>\n"), 0))
+        })
+    }
 
     #[test]
     fn test_parse_intermidiate() {
         let input = r#"x=1 | y= | com1 2 arg2 | com2 'arg3' "arg4" | com3 "a$x$y" $x$y "#;
         let interm = parse_intermediate(input).unwrap();
-        let mut interm_iter = interm.into_iter();
+        let mut interm_iter = interm.items[0].first.commands.clone().into_iter();
 
         let ShellCommandInterm::Assign { name, value } = interm_iter.next().unwrap() else {
             panic!("Expected Assign")
@@ -277,7 +777,7 @@ mod tests {
         assert_eq!(name, "y");
         assert_eq!(value, None);
 
-        let ShellCommandInterm::Execute { name, args } = interm_iter.next().unwrap() else {
+        let ShellCommandInterm::Execute { name, args, .. } = interm_iter.next().unwrap() else {
             panic!("Expected Execute")
         };
         assert_eq!(
@@ -291,7 +791,7 @@ mod tests {
             vec![Arg::String(StringArg::Simple(String::from("arg2")))]
         );
 
-        let ShellCommandInterm::Execute { name, args } = interm_iter.next().unwrap() else {
+        let ShellCommandInterm::Execute { name, args, .. } = interm_iter.next().unwrap() else {
             panic!("Expected Execute")
         };
         assert_eq!(
@@ -310,7 +810,7 @@ mod tests {
             )]))]
         );
 
-        let ShellCommandInterm::Execute { name, args } = interm_iter.next().unwrap() else {
+        let ShellCommandInterm::Execute { name, args, .. } = interm_iter.next().unwrap() else {
             panic!("Expected Execute")
         };
 
@@ -337,7 +837,7 @@ mod tests {
     fn test_parse_full_no_vars() {
         let mut front = Frontend::new();
         let input = r#"echo 1 '2' "3" | cat foo bar"#;
-        let mut commands = front.parse(input).unwrap().commands.into_iter();
+        let mut commands = parse_full(&mut front, input).unwrap().commands.into_iter();
         assert_eq!(
             commands.next().unwrap(),
             CallCommand {
@@ -348,7 +848,8 @@ mod tests {
                     String::from("1"),
                     String::from("2"),
                     String::from("3")
-                ]
+                ],
+                redirects: vec![],
             }
         );
         assert_eq!(
@@ -360,7 +861,8 @@ mod tests {
                     String::from("cat"),
                     String::from("foo"),
                     String::from("bar")
-                ]
+                ],
+                redirects: vec![],
             }
         );
     }
@@ -369,7 +871,7 @@ mod tests {
     fn test_parse_full_assign_change_state() {
         let mut front = Frontend::new();
         let input = r#"x=1"#;
-        front.parse(input).unwrap();
+        parse_full(&mut front, input).unwrap();
         let mut expected_env = Environment::new();
         expected_env.set("x", String::from("1"));
 
@@ -380,13 +882,14 @@ mod tests {
     fn test_parse_full_assign_is_not_visible() {
         let mut front = Frontend::new();
         let input = r#"x=1 | echo $x"#;
-        let mut commands = front.parse(input).unwrap().commands.into_iter();
+        let mut commands = parse_full(&mut front, input).unwrap().commands.into_iter();
         assert_eq!(
             commands.next().unwrap(),
             CallCommand {
                 envs: HashMap::new(),
                 command: crate::ir::Command::Builtin(Box::<EchoCommand>::default()),
-                argv: vec![String::from("echo"), String::from("")]
+                argv: vec![String::from("echo")],
+                redirects: vec![],
             }
         );
     }
@@ -395,16 +898,17 @@ mod tests {
     fn test_parse_full_assign_is_visible() {
         let mut front = Frontend::new();
         let input = r#"x=1"#;
-        front.parse(input).unwrap();
+        parse_full(&mut front, input).unwrap();
 
         let input = r#"echo $x"#;
-        let mut commands = front.parse(input).unwrap().commands.into_iter();
+        let mut commands = parse_full(&mut front, input).unwrap().commands.into_iter();
         assert_eq!(
             commands.next().unwrap(),
             CallCommand {
                 envs: HashMap::new(),
                 command: crate::ir::Command::Builtin(Box::<EchoCommand>::default()),
-                argv: vec![String::from("echo"), String::from("1")]
+                argv: vec![String::from("echo"), String::from("1")],
+                redirects: vec![],
             }
         );
     }
@@ -413,10 +917,10 @@ mod tests {
     fn test_parse_full_compund_compilation_works() {
         let mut front = Frontend::new();
         let input = r#"x=1 y=2 x=3 z=4"#;
-        front.parse(input).unwrap();
+        parse_full(&mut front, input).unwrap();
 
         let input = r#"echo $x$y$z "name$x$y$z""#;
-        let mut commands = front.parse(input).unwrap().commands.into_iter();
+        let mut commands = parse_full(&mut front, input).unwrap().commands.into_iter();
         assert_eq!(
             commands.next().unwrap(),
             CallCommand {
@@ -426,8 +930,113 @@ mod tests {
                     String::from("echo"),
                     String::from("324"),
                     String::from("name324")
-                ]
+                ],
+                redirects: vec![],
             }
         );
     }
+
+    #[test]
+    fn test_parse_full_prefix_assignments() {
+        let mut front = Frontend::new();
+        parse_full(&mut front, "x=1").unwrap();
+        let input = r#"y=$x z= make $y"#;
+        let mut commands = parse_full(&mut front, input).unwrap().commands.into_iter();
+        assert_eq!(
+            commands.next().unwrap(),
+            CallCommand {
+                envs: HashMap::from([
+                    (String::from("y"), String::from("1")),
+                    (String::from("z"), String::new()),
+                ]),
+                command: crate::ir::Command::Call,
+                argv: vec![String::from("make")],
+                redirects: vec![],
+            }
+        );
+        assert_eq!(front.env().get("x"), "1");
+        assert_eq!(front.env().get("y"), "");
+    }
+
+    #[test]
+    fn test_parse_full_command_substitution() {
+        let mut front = Frontend::new();
+        parse_full(&mut front, "x=1").unwrap();
+        let input = r#"echo "$(a $x; b && c &)"d "e `f` $(g "$(h)")""#;
+        let pipe = parse_full(&mut front, input).unwrap();
+        assert_eq!(
+            pipe.commands[0].argv,
+            vec![
+                String::from("echo"),
+                String::from("<a $x; b && c &>d"),
+                String::from("e <f> <g \"$(h)\">"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_full_redirects() {
+        let mut front = Frontend::new();
+        parse_full(&mut front, "x=out").unwrap();
+        let input = r#"echo a >$x 2>&1 <in >>log 3<>rw 2>&- &>all"#;
+        let pipe = parse_full(&mut front, input).unwrap();
+        let command = pipe.commands.into_iter().next().unwrap();
+        assert_eq!(
+            command.redirects,
+            vec![
+                Redirect::Write(1, String::from("out")),
+                Redirect::Dup(2, 1),
+                Redirect::Read(0, String::from("in")),
+                Redirect::Append(1, String::from("log")),
+                Redirect::ReadWrite(3, String::from("rw")),
+                Redirect::Close(2),
+                Redirect::Write(1, String::from("all")),
+                Redirect::Dup(2, 1),
+            ]
+        );
+        assert!(parse_full(&mut front, "echo a 2>&x").is_err());
+        assert!(parse_full(&mut front, "> out").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_list() {
+        let list = parse_intermediate("a && ! b | c || d; e\nf").unwrap();
+        assert_eq!(list.items.len(), 3);
+
+        let and_or = &list.items[0];
+        assert!(!and_or.first.negated);
+        let operators: Vec<_> = and_or.rest.iter().map(|(op, _)| *op).collect();
+        assert_eq!(operators, vec![ListOperator::And, ListOperator::Or]);
+        assert!(and_or.rest[0].1.negated);
+        assert_eq!(and_or.rest[0].1.commands.len(), 2);
+
+        assert!(parse_intermediate("").unwrap().items.is_empty());
+        assert!(parse_intermediate("a &&").is_err());
+    }
+
+    #[test]
+    fn test_parse_compound_commands() {
+        for input in [
+            "if a; then b; elif c; then d & else e; fi",
+            "while a; do b; done >out | until c; do d; done",
+            "for x in a \"$b\"; do c; done; for y; do d; done",
+            "case $x in a | b) c;; *) ;; esac",
+            "{ a; b; } && (c; d)",
+            "f() { a \"$1\"; } >&2",
+        ] {
+            assert_eq!(parse_intermediate(input).unwrap().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_parse_incomplete() {
+        for input in ["echo 'a", "echo a \\\n", "a &&", "echo $(b"] {
+            assert!(
+                parse_intermediate(input).unwrap_err().is_incomplete(),
+                "{input}"
+            );
+        }
+        assert!(!parse_intermediate("echo )").unwrap_err().is_incomplete());
+        assert_eq!(parse_intermediate("echo a \\\nb").unwrap().items.len(), 1);
+    }
 }
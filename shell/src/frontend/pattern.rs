@@ -0,0 +1,185 @@
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::Path,
+};
+
+use glob::{MatchOptions, Pattern};
+
+/// Where `${name/pattern/string}` replaces matches of the pattern.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    /// `/`, the first match.
+    First,
+    /// `//`, every match.
+    All,
+    /// `/#`, a match at the start of the value.
+    Start,
+    /// `/%`, a match at the end of the value.
+    End,
+}
+
+impl Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anchor::First => write!(f, "/"),
+            Anchor::All => write!(f, "//"),
+            Anchor::Start => write!(f, "/#"),
+            Anchor::End => write!(f, "/%"),
+        }
+    }
+}
+
+/// Byte offsets of every character boundary of `value`, including its end.
+fn boundaries(value: &str) -> Vec<usize> {
+    value
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain([value.len()])
+        .collect()
+}
+
+/// Removes the shortest or the longest prefix matching `pattern`.
+pub fn remove_prefix<'a>(value: &'a str, pattern: &Pattern, longest: bool) -> &'a str {
+    let mut ends = boundaries(value);
+    if longest {
+        ends.reverse();
+    }
+    ends.into_iter()
+        .find(|&end| pattern.matches(&value[..end]))
+        .map_or(value, |end| &value[end..])
+}
+
+/// Removes the shortest or the longest suffix matching `pattern`.
+pub fn remove_suffix<'a>(value: &'a str, pattern: &Pattern, longest: bool) -> &'a str {
+    let mut starts = boundaries(value);
+    if !longest {
+        starts.reverse();
+    }
+    starts
+        .into_iter()
+        .find(|&start| pattern.matches(&value[start..]))
+        .map_or(value, |start| &value[..start])
+}
+
+/// Replaces the longest matches of `pattern` according to `anchor`. An empty
+/// pattern matches nothing.
+pub fn replace(value: &str, pattern: &Pattern, replacement: &str, anchor: Anchor) -> String {
+    if pattern.as_str().is_empty() {
+        return value.to_string();
+    }
+    let bounds = boundaries(value);
+    let longest_match = |start: usize| {
+        bounds
+            .iter()
+            .rev()
+            .copied()
+            .take_while(|&end| end >= start)
+            .filter(|&end| anchor != Anchor::End || end == value.len())
+            .find(|&end| pattern.matches(&value[start..end]))
+    };
+
+    let mut result = String::new();
+    let mut copied = 0;
+    for &start in &bounds {
+        if start < copied {
+            continue;
+        }
+        if let Some(end) = longest_match(start) {
+            result.push_str(&value[copied..start]);
+            result.push_str(replacement);
+            copied = end;
+            if anchor != Anchor::All {
+                break;
+            }
+        }
+        if anchor == Anchor::Start {
+            break;
+        }
+    }
+    result.push_str(&value[copied..]);
+    result
+}
+
+/// Paths that match a pathname pattern, relative to `cwd` unless the pattern
+/// is absolute, sorted.
+///
+/// Each component of the pattern is matched against the entries of the
+/// directories matched so far. Names starting with `.` only match a component
+/// that starts with a literal `.`.
+pub fn expand_paths(pattern: &str, cwd: &Path) -> Vec<String> {
+    let options = MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: true,
+    };
+    let (mut paths, components) = match pattern.strip_prefix('/') {
+        Some(components) => (vec![String::from("/")], components),
+        None => (vec![String::new()], pattern),
+    };
+    for component in components.split('/') {
+        let mut matched = Vec::new();
+        for path in paths {
+            let join = |name: &str| match path.is_empty() || path.ends_with('/') {
+                true => format!("{path}{name}"),
+                false => format!("{path}/{name}"),
+            };
+            if !component.contains(['*', '?', '[']) {
+                matched.push(join(component));
+                continue;
+            }
+            let (Ok(pattern), Ok(entries)) =
+                (Pattern::new(component), fs::read_dir(cwd.join(&path)))
+            else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if pattern.matches_with(&name, options) {
+                    matched.push(join(&name));
+                }
+            }
+        }
+        paths = matched;
+    }
+    paths.retain(|path| fs::symlink_metadata(cwd.join(path)).is_ok());
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(pattern: &str) -> Pattern {
+        Pattern::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn test_remove_prefix_and_suffix() {
+        let path = "dir/sub/file.tar.gz";
+        assert_eq!(
+            remove_prefix(path, &pattern("*/"), false),
+            "sub/file.tar.gz"
+        );
+        assert_eq!(remove_prefix(path, &pattern("*/"), true), "file.tar.gz");
+        assert_eq!(
+            remove_suffix(path, &pattern(".*"), false),
+            "dir/sub/file.tar"
+        );
+        assert_eq!(remove_suffix(path, &pattern(".*"), true), "dir/sub/file");
+        assert_eq!(remove_suffix(path, &pattern("x"), true), path);
+    }
+
+    #[test]
+    fn test_replace() {
+        assert_eq!(replace("aXbXc", &pattern("X"), "-", Anchor::First), "a-bXc");
+        assert_eq!(replace("aXbXc", &pattern("X"), "-", Anchor::All), "a-b-c");
+        assert_eq!(replace("aXbXc", &pattern("X*"), "", Anchor::First), "a");
+        assert_eq!(replace("aXbXc", &pattern("a"), "-", Anchor::Start), "-XbXc");
+        assert_eq!(replace("aXbXc", &pattern("X"), "-", Anchor::Start), "aXbXc");
+        assert_eq!(replace("aXbXc", &pattern("X?"), "-", Anchor::End), "aXb-");
+        assert_eq!(replace("абв", &pattern("б"), "b", Anchor::All), "аbв");
+        assert_eq!(replace("abc", &pattern(""), "-", Anchor::All), "abc");
+    }
+}
@@ -0,0 +1,115 @@
+//! Tilde expansion of `~`, `~user`, `~+` and `~-` at the start of words, and
+//! after `:` in assignments.
+
+use nix::unistd::User;
+
+use super::{
+    brace::{into_word, units, Unit},
+    env::Environment,
+    Arg, CompoundArg, StringArg,
+};
+
+/// Replaces the tilde prefixes of `word` with the directories they name.
+///
+/// A prefix runs up to the first `/`, or `:` in an `assignment`, and is left
+/// as it is if part of it is quoted or it names no directory.
+pub fn expand(word: CompoundArg, assignment: bool, env: &Environment) -> CompoundArg {
+    let units = units(word);
+    let mut expanded = Vec::new();
+    let mut index = 0;
+    while index < units.len() {
+        let at_start = index == 0 || (assignment && units[index - 1] == Unit::Char(':'));
+        if !at_start || units[index] != Unit::Char('~') {
+            expanded.push(units[index].clone());
+            index += 1;
+            continue;
+        }
+        let mut end = index + 1;
+        let mut name = String::new();
+        while let Some(Unit::Char(c)) = units.get(end) {
+            if *c == '/' || (assignment && *c == ':') {
+                break;
+            }
+            name.push(*c);
+            end += 1;
+        }
+        let quoted = matches!(units.get(end), Some(Unit::Part(_)));
+        match directory(&name, env).filter(|_| !quoted) {
+            Some(dir) => {
+                expanded.push(Unit::Part(Arg::String(StringArg::SingleQuoted(dir))));
+                index = end;
+            }
+            None => {
+                expanded.push(Unit::Char('~'));
+                index += 1;
+            }
+        }
+    }
+    into_word(expanded)
+}
+
+/// The directory that the tilde prefix `~name` stands for.
+fn directory(name: &str, env: &Environment) -> Option<String> {
+    match name {
+        "" => env.lookup("HOME").or_else(|| {
+            let user = User::from_uid(nix::unistd::getuid()).ok()??;
+            Some(user.dir.to_string_lossy().into_owned())
+        }),
+        "+" => env.lookup("PWD"),
+        "-" => env.lookup("OLDPWD"),
+        name => {
+            let user = User::from_name(name).ok()??;
+            Some(user.dir.to_string_lossy().into_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+    use crate::frontend::{env::Environment, parse_intermediate, ShellCommandInterm};
+
+    fn expand_words(input: &str, env: &Environment) -> Vec<String> {
+        let list = parse_intermediate(input).unwrap();
+        match list.items[0].first.commands[0].clone() {
+            ShellCommandInterm::Execute { args, .. } => args
+                .into_iter()
+                .map(|word| expand(word, false, env).to_string())
+                .collect(),
+            ShellCommandInterm::Assign { value, .. } => {
+                vec![expand(value.unwrap(), true, env).to_string()]
+            }
+            ShellCommandInterm::Compound { .. } | ShellCommandInterm::Function { .. } => {
+                panic!("not a simple command")
+            }
+        }
+    }
+
+    #[test]
+    fn test_tilde_expansion() {
+        let mut env = Environment::new();
+        env.set("HOME", String::from("/home/me"));
+        env.set("PWD", String::from("/work"));
+        assert_eq!(
+            expand_words(
+                "echo ~ ~/bin ~+/x ~- a~ ~root '~' ~'root' ~nobody-here",
+                &env
+            ),
+            [
+                "'/home/me'",
+                "'/home/me'/bin",
+                "'/work'/x",
+                "~-",
+                "a~",
+                "'/root'",
+                "'~'",
+                "~'root'",
+                "~nobody-here"
+            ]
+        );
+        assert_eq!(
+            expand_words("PATH=~/bin:~:/usr/~", &env),
+            ["'/home/me'/bin:'/home/me':/usr/~"]
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+};
+
+use os_pipe::{dup_stdin, PipeReader};
+
+use crate::editor::Editor;
+
+/// Source of the commands run by the shell.
+pub enum Input {
+    /// Standard input, read a byte at a time, so that commands started by the
+    /// shell get the input that follows their own line.
+    Stdin(PipeReader),
+    /// Lines of a script file or of a `-c` command string.
+    Text(VecDeque<String>),
+    /// The terminal, read with the line editor.
+    Editor(Box<Editor>),
+}
+
+impl Input {
+    pub fn stdin() -> std::io::Result<Self> {
+        Ok(Input::Stdin(dup_stdin()?))
+    }
+
+    pub fn text(text: &str) -> Self {
+        Input::Text(text.split_inclusive('\n').map(String::from).collect())
+    }
+
+    /// Reads the next line including its newline, or returns `None` at the end
+    /// of the input. `prompt` is shown on stderr before reading from standard
+    /// input or the terminal.
+    pub fn next_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        match self {
+            Input::Stdin(reader) => {
+                if !prompt.is_empty() {
+                    eprint!("{prompt}");
+                    std::io::stderr().flush()?;
+                }
+                let mut line = Vec::new();
+                let mut byte = [0];
+                while reader.read(&mut byte)? == 1 {
+                    line.push(byte[0]);
+                    if byte[0] == b'\n' {
+                        break;
+                    }
+                }
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+            }
+            Input::Text(lines) => Ok(lines.pop_front()),
+            Input::Editor(editor) => editor.read_line(prompt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Input;
+
+    #[test]
+    fn test_text_lines() -> std::io::Result<()> {
+        let mut input = Input::text("echo a\necho b");
+        assert_eq!(input.next_line("")?.as_deref(), Some("echo a\n"));
+        assert_eq!(input.next_line("")?.as_deref(), Some("echo b"));
+        assert_eq!(input.next_line("")?, None);
+        Ok(())
+    }
+}
@@ -1,13 +1,206 @@
 use std::{
     collections::HashMap,
     error::Error,
-    fmt::Debug,
+    fmt::{self, Debug, Display},
     io::{Read, Write},
 };
 
+use crate::backend::{ExitStatus, Shell};
 use crate::builtins::{
-    cat::CatCommand, echo::EchoCommand, exit::ExitCommand, pwd::PwdCommand, wc::WcCommand,
+    alias::AliasCommand, bg::BgCommand, cat::CatCommand, cd::CdCommand, echo::EchoCommand,
+    env::EnvCommand, exit::ExitCommand, export::ExportCommand, fg::FgCommand, grep::GrepCommand,
+    jobs::JobsCommand, kill::KillCommand, local::LocalCommand, pwd::PwdCommand,
+    r#break::BreakCommand, r#continue::ContinueCommand, r#let::LetCommand, r#return::ReturnCommand,
+    set::SetCommand, shift::ShiftCommand, unalias::UnaliasCommand, unset::UnsetCommand,
+    wait::WaitCommand, wc::WcCommand,
 };
+use crate::frontend::{CompoundArg, Environment, ShellCommandInterm};
+
+/// Statements of an input, separated by `;` or newlines, that run one after another.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CommandList {
+    pub items: Vec<AndOrList>,
+}
+
+/// Pipelines joined with `&&` and `||`, evaluated from left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AndOrList {
+    pub first: Pipeline,
+    pub rest: Vec<(ListOperator, Pipeline)>,
+    /// Whether the list is terminated with `&` and runs as a background job.
+    pub background: bool,
+}
+
+impl Display for CommandList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, and_or) in self.items.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{and_or}")?;
+            if and_or.background {
+                write!(f, " &")?;
+            } else if index + 1 < self.items.len() {
+                write!(f, ";")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for AndOrList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first)?;
+        for (operator, pipeline) in &self.rest {
+            match operator {
+                ListOperator::And => write!(f, " && {pipeline}")?,
+                ListOperator::Or => write!(f, " || {pipeline}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListOperator {
+    /// `&&`, runs the next pipeline only if the previous one succeeded.
+    And,
+    /// `||`, runs the next pipeline only if the previous one failed.
+    Or,
+}
+
+/// Pipeline that is not compiled yet.
+///
+/// Compilation happens right before execution, so that expansions observe
+/// the effects of the statements that ran before.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    /// Whether the exit status is inverted with `!`.
+    pub negated: bool,
+    pub commands: Vec<ShellCommandInterm>,
+}
+
+impl Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "! ")?;
+        }
+        for (index, command) in self.commands.iter().enumerate() {
+            if index > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "{command}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Command that groups lists of commands, evaluated by the backend.
+///
+/// The lists are compiled only when they run, like the rest of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompoundCommand {
+    /// `{ list; }`, runs in the shell itself.
+    Brace(CommandList),
+    /// `( list )`, runs with a copy of the shell state.
+    Subshell(CommandList),
+    /// `if list; then list; elif list; then list; else list; fi`
+    If {
+        conditionals: Vec<(CommandList, CommandList)>,
+        else_branch: Option<CommandList>,
+    },
+    /// `while list; do list; done`, or `until` if `until` is set.
+    Loop {
+        until: bool,
+        condition: CommandList,
+        body: CommandList,
+    },
+    /// `for name in words; do list; done`, without words over the positional parameters.
+    For {
+        name: String,
+        words: Option<Vec<CompoundArg>>,
+        body: CommandList,
+    },
+    /// `case word in pattern | pattern) list;; esac`
+    Case {
+        word: CompoundArg,
+        arms: Vec<(Vec<CompoundArg>, CommandList)>,
+    },
+}
+
+/// Writes a list followed by `;`, unless it already ends with `&`.
+fn write_terminated(f: &mut fmt::Formatter<'_>, list: &CommandList) -> fmt::Result {
+    match list.items.last() {
+        Some(and_or) if !and_or.background => write!(f, "{list};"),
+        _ => write!(f, "{list}"),
+    }
+}
+
+impl Display for CompoundCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompoundCommand::Brace(list) => {
+                write!(f, "{{ ")?;
+                write_terminated(f, list)?;
+                write!(f, " }}")
+            }
+            CompoundCommand::Subshell(list) => write!(f, "({list})"),
+            CompoundCommand::If {
+                conditionals,
+                else_branch,
+            } => {
+                for (index, (condition, body)) in conditionals.iter().enumerate() {
+                    write!(f, "{} ", if index == 0 { "if" } else { " elif" })?;
+                    write_terminated(f, condition)?;
+                    write!(f, " then ")?;
+                    write_terminated(f, body)?;
+                }
+                if let Some(body) = else_branch {
+                    write!(f, " else ")?;
+                    write_terminated(f, body)?;
+                }
+                write!(f, " fi")
+            }
+            CompoundCommand::Loop {
+                until,
+                condition,
+                body,
+            } => {
+                write!(f, "{} ", if *until { "until" } else { "while" })?;
+                write_terminated(f, condition)?;
+                write!(f, " do ")?;
+                write_terminated(f, body)?;
+                write!(f, " done")
+            }
+            CompoundCommand::For { name, words, body } => {
+                write!(f, "for {name}")?;
+                if let Some(words) = words {
+                    write!(f, " in")?;
+                    for word in words {
+                        write!(f, " {word}")?;
+                    }
+                }
+                write!(f, "; do ")?;
+                write_terminated(f, body)?;
+                write!(f, " done")
+            }
+            CompoundCommand::Case { word, arms } => {
+                write!(f, "case {word} in")?;
+                for (patterns, body) in arms {
+                    write!(f, " ")?;
+                    for (index, pattern) in patterns.iter().enumerate() {
+                        if index > 0 {
+                            write!(f, " | ")?;
+                        }
+                        write!(f, "{pattern}")?;
+                    }
+                    write!(f, ") {body};;")?;
+                }
+                write!(f, " esac")
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct PipeCommand {
@@ -19,24 +212,91 @@ pub struct CallCommand {
     pub envs: HashMap<String, String>,
     pub command: Command,
     pub argv: Vec<String>,
+    /// Redirections applied from left to right before the command starts.
+    pub redirects: Vec<Redirect>,
+}
+
+/// Redirection of a file descriptor of a single command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Redirect {
+    /// `[fd]<path`
+    Read(u16, String),
+    /// `[fd]>path` and `[fd]>|path`
+    Write(u16, String),
+    /// `[fd]>>path`
+    Append(u16, String),
+    /// `[fd]<>path`
+    ReadWrite(u16, String),
+    /// `[fd]>&target` and `[fd]<&target`
+    Dup(u16, u16),
+    /// `[fd]>&-` and `[fd]<&-`
+    Close(u16),
 }
 
 #[derive(Debug)]
 pub enum Command {
     Call,
     Builtin(Box<dyn BuiltinCommand + Send>),
+    Compound(Box<CompoundCommand>),
+    /// Call of a shell function, with the body of its definition.
+    Function(Box<ShellCommandInterm>),
 }
 
+type NewBuiltin = fn() -> Box<dyn BuiltinCommand + Send>;
+
+/// Builtin commands by name, sorted.
+const BUILTINS: &[(&str, NewBuiltin)] = &[
+    ("alias", || Box::<AliasCommand>::default()),
+    ("bg", || Box::<BgCommand>::default()),
+    ("break", || Box::<BreakCommand>::default()),
+    ("cat", || Box::<CatCommand>::default()),
+    ("cd", || Box::<CdCommand>::default()),
+    ("continue", || Box::<ContinueCommand>::default()),
+    ("echo", || Box::<EchoCommand>::default()),
+    ("env", || Box::<EnvCommand>::default()),
+    ("exit", || Box::<ExitCommand>::default()),
+    ("export", || Box::<ExportCommand>::default()),
+    ("fg", || Box::<FgCommand>::default()),
+    ("grep", || Box::<GrepCommand>::default()),
+    ("jobs", || Box::<JobsCommand>::default()),
+    ("kill", || Box::<KillCommand>::default()),
+    ("let", || Box::<LetCommand>::default()),
+    ("local", || Box::<LocalCommand>::default()),
+    ("pwd", || Box::<PwdCommand>::default()),
+    ("return", || Box::<ReturnCommand>::default()),
+    ("set", || Box::<SetCommand>::default()),
+    ("shift", || Box::<ShiftCommand>::default()),
+    ("unalias", || Box::<UnaliasCommand>::default()),
+    ("unset", || Box::<UnsetCommand>::default()),
+    ("wait", || Box::<WaitCommand>::default()),
+    ("wc", || Box::<WcCommand>::default()),
+];
+
 impl Command {
-    pub fn from_name(name: &str) -> Self {
-        match name {
-            "cat" => Command::Builtin(Box::<CatCommand>::default()),
-            "echo" => Command::Builtin(Box::<EchoCommand>::default()),
-            "exit" => Command::Builtin(Box::<ExitCommand>::default()),
-            "pwd" => Command::Builtin(Box::<PwdCommand>::default()),
-            "wc" => Command::Builtin(Box::<WcCommand>::default()),
-            _ => Command::Call,
+    /// Looks up a command by the name in `argv`: builtins that handle the
+    /// arguments come first, then the functions defined in `env`, and external
+    /// commands last.
+    pub fn from_argv(argv: &[String], env: &Environment) -> Self {
+        let name = &argv[0];
+        if let Some(builtin) = Self::builtin(name).filter(|builtin| builtin.handles(&argv[1..])) {
+            return Command::Builtin(builtin);
         }
+        match env.function(name) {
+            Some(body) => Command::Function(Box::new(body.clone())),
+            None => Command::Call,
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Box<dyn BuiltinCommand + Send>> {
+        BUILTINS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, new)| new())
+    }
+
+    /// Names of the builtin commands, sorted.
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTINS.iter().map(|(name, _)| *name)
     }
 }
 
@@ -47,6 +307,8 @@ impl PartialEq for Command {
             (Command::Builtin(cmd_self), Command::Builtin(cmd_other)) => {
                 cmd_self.tag() == cmd_other.tag()
             }
+            (Command::Compound(cmd_self), Command::Compound(cmd_other)) => cmd_self == cmd_other,
+            (Command::Function(cmd_self), Command::Function(cmd_other)) => cmd_self == cmd_other,
             _ => false,
         }
     }
@@ -55,11 +317,23 @@ impl PartialEq for Command {
 pub trait BuiltinCommand: Debug {
     fn tag(&self) -> &'static str;
 
+    /// Flags accepted by the builtin, offered by completion.
+    fn flags(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether the builtin handles the arguments `args`, without the command
+    /// name. Otherwise the external command of the same name runs instead.
+    fn handles(&self, _args: &[String]) -> bool {
+        true
+    }
+
     fn exec(
         &self,
         args: Vec<String>,
+        shell: &mut Shell,
         stdin: &mut dyn Read,
         stderr: &mut dyn Write,
         stdout: &mut dyn Write,
-    ) -> Result<(), Box<dyn Error + Sync + Send>>;
+    ) -> Result<ExitStatus, Box<dyn Error + Sync + Send>>;
 }
@@ -1,40 +1,191 @@
-use std::error::Error;
+use std::{
+    error::Error,
+    io::{ErrorKind, IsTerminal},
+    process::ExitCode,
+};
 
+use clap::Parser;
 use os_pipe::{dup_stdin, dup_stdout};
 
+use crate::{
+    backend::{signals, Backend, Stream},
+    editor::{prompt, Completer, Editor, Keymap},
+    frontend::{Control, Frontend},
+    input::Input,
+};
+
 mod backend;
 mod builtins;
+mod editor;
 mod frontend;
+mod input;
 mod ir;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut frontend = frontend::Frontend::new();
-    let backend = backend::Backend::new();
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Read commands from the first operand instead of a file.
+    #[arg(short = 'c')]
+    command: bool,
+
+    /// Read commands from standard input, all operands are positional parameters.
+    #[arg(short = 's')]
+    stdin: bool,
 
-    for line in std::io::stdin().lines() {
-        let line = line?;
+    /// Run interactively, with job control.
+    #[arg(short = 'i')]
+    interactive: bool,
+
+    /// Script file or command string, followed by `$0` for `-c` and the positional parameters.
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    operands: Vec<String>,
+}
 
-        let command = match frontend.parse(&line) {
-            Ok(command) => command,
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let args = Args::parse();
+    let mut frontend = Frontend::new();
+    *frontend.env_mut() = frontend::Environment::from_process();
+    let mut shell_name = std::env::args().next().unwrap_or_default();
+
+    let mut operands = args.operands.into_iter();
+    let mut input = if args.command {
+        let Some(command) = operands.next() else {
+            eprintln!("shell: -c: option requires an argument");
+            return Ok(ExitCode::from(2));
+        };
+        if let Some(name) = operands.next() {
+            shell_name = name;
+        }
+        Input::text(&command)
+    } else if args.stdin || operands.len() == 0 {
+        Input::stdin()?
+    } else {
+        let path = operands.next().unwrap_or_default();
+        match std::fs::read_to_string(&path) {
+            Ok(script) => {
+                shell_name = path;
+                Input::text(&script)
+            }
             Err(err) => {
-                eprintln!("{}", err);
+                eprintln!("shell: {path}: {err}");
+                return Ok(ExitCode::from(127));
+            }
+        }
+    };
+    frontend.env_mut().set_shell_name(shell_name);
+    frontend.env_mut().set_positional(operands.collect());
+
+    let on_terminal = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    let interactive = args.interactive || (matches!(input, Input::Stdin(_)) && on_terminal);
+    if interactive {
+        frontend.env_mut().set_interactive(true);
+        frontend.env_mut().set_option("emacs", true);
+        if matches!(input, Input::Stdin(_)) && on_terminal {
+            input = Input::Editor(Box::new(Editor::from_env(frontend.env())));
+        }
+    }
+    signals::install();
+    let backend = match interactive {
+        true => Backend::with_job_control(),
+        false => Backend::new(),
+    };
+
+    let code = run(&mut input, &mut frontend, &backend, interactive)?;
+    Ok(ExitCode::from(code as u8))
+}
+
+/// Runs the commands of `input` until its end and returns the exit status of
+/// the last one. Syntax errors end a non-interactive shell with status 2, and
+/// SIGINT or SIGQUIT with `128+N`.
+///
+/// Interactive shells prompt with `PS1`, and with `PS2` for lines that continue
+/// a command.
+fn run(
+    input: &mut Input,
+    frontend: &mut Frontend,
+    backend: &Backend,
+    interactive: bool,
+) -> Result<i32, Box<dyn Error>> {
+    let mut source = String::new();
+    loop {
+        let prompt = match (interactive, source.is_empty()) {
+            (false, _) => String::new(),
+            (true, true) => prompt::expand(&ps(frontend, "PS1", "\\s-\\$ "), frontend.env()),
+            (true, false) => prompt::expand(&ps(frontend, "PS2", "> "), frontend.env()),
+        };
+        if let Input::Editor(editor) = input {
+            editor.set_keymap(match frontend.env().option("vi") {
+                true => Keymap::Vi,
+                false => Keymap::Emacs,
+            });
+            editor.set_completer(Completer::new(frontend.env(), frontend.current_dir()));
+        }
+        let line = match input.next_line(&prompt) {
+            Err(err) if err.kind() == ErrorKind::Interrupted => {
+                source.clear();
+                frontend.env_mut().set_status(130);
                 continue;
             }
+            line => line?,
         };
+        let at_end = line.is_none();
+        source.push_str(&line.unwrap_or_default());
+        if source.is_empty() {
+            break;
+        }
 
-        match backend.exec(command, dup_stdin()?, dup_stdout()?) {
-            Ok(exit_status) => match exit_status.code() {
-                Some(code) if code != 0 => {
-                    eprintln!("exited with code {}", code);
-                }
-                _ => {}
-            },
+        let list = match frontend.parse(&source) {
+            Err(err) if err.is_incomplete() && !at_end => continue,
+            result => {
+                source.clear();
+                result
+            }
+        };
+        let list = match list {
+            Ok(list) => list,
             Err(err) => {
-                eprintln!("shell error: {}", err);
+                eprintln!("{}", err);
+                if !interactive {
+                    return Ok(2);
+                }
+                frontend.env_mut().set_status(2);
                 continue;
             }
         };
+
+        if interactive {
+            signals::clear();
+        }
+        match backend.exec_list(list, frontend, dup_stdin()?, dup_stdout()?, Stream::Stderr) {
+            // Interrupts come from the user and `exit` ends the shell, neither
+            // needs a report.
+            Ok(exit_status)
+                if interactive
+                    && !exit_status.success()
+                    && !exit_status.signal().is_some_and(signals::is_interrupt)
+                    && frontend.env().control().is_none() =>
+            {
+                eprintln!("{exit_status}");
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("shell error: {}", err),
+        };
+        backend.report_finished_jobs();
+        if let Some(Control::Exit(code)) = frontend.env().control() {
+            return Ok(code);
+        }
+        if let (false, Some(signal)) = (interactive, signals::interrupted()) {
+            return Ok(128 + signal as i32);
+        }
     }
 
-    Ok(())
+    Ok(frontend.env().status())
+}
+
+/// The value of the prompt variable `name`, or `default` if it is unset.
+fn ps(frontend: &Frontend, name: &str, default: &str) -> String {
+    frontend
+        .env()
+        .lookup(name)
+        .unwrap_or_else(|| String::from(default))
 }
@@ -0,0 +1,40 @@
+//! Interrupts are process wide, so they are tested on a shell of its own
+//! rather than next to the unit tests.
+
+use std::{
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use nix::{
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+
+#[test]
+fn test_interrupt_cancels_builtin_reading_input() {
+    let mut shell = Command::new(env!("CARGO_BIN_EXE_shell"))
+        .args(["-c", "cat | cat; echo after $?"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The input stays open, so only the interrupt can end `cat`.
+    let _stdin = shell.stdin.take();
+    thread::sleep(Duration::from_millis(300));
+    kill(Pid::from_raw(shell.id() as i32), Signal::SIGINT).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let status = loop {
+        if let Some(status) = shell.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            shell.kill().unwrap();
+            panic!("the builtin kept reading after the interrupt");
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    assert_eq!(status.code(), Some(130));
+}